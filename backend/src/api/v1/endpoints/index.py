@@ -0,0 +1,130 @@
+"""
+Per-file index inspection and removal for clients that upload files.
+
+A file is identified by the path it was uploaded under (stored as the
+`full_path` payload field) together with its org. Chunk ids are UUIDs, so
+they can't be derived from the path.
+"""
+from fastapi import APIRouter, Depends, HTTPException, Query
+from typing import Dict, List, Optional
+from pydantic import BaseModel
+
+from src.api.v1.dependencies import verify_admin
+from src.db.qdrant import get_qdrant_client
+from src.services.admin.admin_store import get_admin_store
+from src.services.ingestion.indexer import Indexer
+
+router = APIRouter()
+
+class ChunkInfo(BaseModel):
+    chunk_id: str
+    chunk_index: Optional[int] = None
+    start_line: int = 0
+    end_line: int = 0
+    language: Optional[str] = None
+    chunk_type: Optional[str] = None
+
+class FileChunksResponse(BaseModel):
+    path: str
+    org_id: str
+    chunks: List[ChunkInfo]
+    total: int
+
+class DeleteFileResponse(BaseModel):
+    status: str  # deleted, not_found
+    path: str
+    org_id: str
+    chunks_removed: int
+
+class ClearIndexResponse(BaseModel):
+    status: str  # cleared
+    org_id: str
+    chunks_removed: int
+
+class IndexStatsResponse(BaseModel):
+    status: str  # ok, degraded
+    indexed_chunks: int
+    stores: Dict[str, int]
+    bm25: dict
+
+def get_indexer() -> Indexer:
+    return Indexer(qdrant_client=get_qdrant_client())
+
+def get_store_ids() -> List[str]:
+    """Configured stores, plus the default one uploads fall back to."""
+    return sorted(set(get_admin_store().get_stores()) | {"public"})
+
+@router.get("/stats", response_model=IndexStatsResponse)
+async def index_stats(
+    store_ids: List[str] = Depends(get_store_ids),
+    indexer: Indexer = Depends(get_indexer)
+):
+    """
+    Chunk counts for the whole index and per store, with BM25 index health.
+    """
+    try:
+        counts = indexer.stats(store_ids)
+    except Exception as e:
+        raise HTTPException(status_code=503, detail=f"Index unavailable: {e}")
+
+    tantivy = indexer.tantivy_client
+    bm25 = tantivy.health() if tantivy else {"status": "unavailable"}
+    status = "ok" if bm25.get("status") == "healthy" else "degraded"
+
+    return {"status": status, **counts, "bm25": bm25}
+
+@router.get("/chunks", response_model=FileChunksResponse)
+async def file_chunks(
+    path: str = Query(..., description="Path the file was uploaded under"),
+    org_id: str = "public",
+    indexer: Indexer = Depends(get_indexer)
+):
+    """
+    List the chunks indexed for one file, in line order.
+    """
+    try:
+        chunks = indexer.file_chunks(path, org_id)
+    except Exception as e:
+        raise HTTPException(status_code=503, detail=f"Index unavailable: {e}")
+
+    return {
+        "path": path,
+        "org_id": org_id,
+        "chunks": chunks,
+        "total": len(chunks)
+    }
+
+@router.delete("/file", response_model=DeleteFileResponse)
+async def delete_file(
+    path: str = Query(..., description="Path the file was uploaded under"),
+    org_id: str = "public",
+    admin: dict = Depends(verify_admin),
+    indexer: Indexer = Depends(get_indexer)
+):
+    """
+    Remove every chunk of one file from the vector and BM25 indexes.
+
+    Deleting a file that isn't indexed succeeds with status "not_found".
+    """
+    try:
+        result = indexer.delete_file(path, org_id)
+    except Exception as e:
+        raise HTTPException(status_code=503, detail=f"Index unavailable: {e}")
+
+    return {"path": path, "org_id": org_id, **result}
+
+@router.post("/clear", response_model=ClearIndexResponse)
+async def clear_index(
+    org_id: str = Query(..., description="Org whose chunks are removed"),
+    admin: dict = Depends(verify_admin),
+    indexer: Indexer = Depends(get_indexer)
+):
+    """
+    Remove everything indexed for one org from the vector and BM25 indexes.
+    """
+    try:
+        result = indexer.clear_org(org_id)
+    except Exception as e:
+        raise HTTPException(status_code=503, detail=f"Index unavailable: {e}")
+
+    return {"org_id": org_id, **result}
@@ -16,7 +16,7 @@ app = FastAPI(
 # Using http://jaeger:4317 inside docker network
 # setup_telemetry(app, "rice-search-api", os.getenv("OTEL_EXPORTER_OTLP_ENDPOINT", "http://jaeger:4317"))
 
-from src.api.v1.endpoints import ingest, search, files, stores, settings as settings_api
+from src.api.v1.endpoints import ingest, search, files, stores, index, settings as settings_api
 from src.api.v1.endpoints import metrics
 from src.api.v1.endpoints.admin import config as admin_config
 from src.api.v1.endpoints.admin import public as admin_public
@@ -24,6 +24,7 @@ from src.api.v1.endpoints.admin import public as admin_public
 app.include_router(ingest.router, prefix=f"{settings.API_V1_STR}/ingest", tags=["ingestion"])
 app.include_router(search.router, prefix=f"{settings.API_V1_STR}/search", tags=["search"])
 app.include_router(files.router, prefix=f"{settings.API_V1_STR}/files", tags=["files"])
+app.include_router(index.router, prefix=f"{settings.API_V1_STR}/index", tags=["index"])
 app.include_router(stores.router, prefix=f"{settings.API_V1_STR}/stores", tags=["stores"])
 app.include_router(settings_api.router, prefix=f"{settings.API_V1_STR}/settings", tags=["settings"])
 app.include_router(admin_config.router, prefix=f"{settings.API_V1_STR}/admin", tags=["admin"])
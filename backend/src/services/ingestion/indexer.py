@@ -32,6 +32,9 @@ from src.services.search.retriever import embed_texts
 
 logger = logging.getLogger(__name__)
 
+# Chunk ids per Tantivy delete request, well under its body size limit
+TANTIVY_DELETE_BATCH = 1000
+
 
 class Indexer:
     """
@@ -148,45 +151,12 @@ class Indexer:
             Dict with status and statistics
         """
         import pathlib
-        from qdrant_client.models import Filter, FieldCondition, MatchValue
 
         # 0. Delete existing chunks for this file path (ensures replacement, not duplication)
         try:
-            logger.info(f"Checking for existing chunks for file: {display_path}")
-            existing_points = self.qdrant.scroll(
-                collection_name=self.collection_name,
-                scroll_filter=Filter(
-                    must=[
-                        FieldCondition(key="full_path", match=MatchValue(value=display_path)),
-                        FieldCondition(key="org_id", match=MatchValue(value=org_id))
-                    ]
-                ),
-                limit=10000,
-                with_payload=False
-            )[0]
-
-            if existing_points:
-                chunk_ids = [str(p.id) for p in existing_points]
-                logger.info(f"Deleting {len(chunk_ids)} existing chunks for {display_path}")
-
-                # Delete from Qdrant
-                self.qdrant.delete(
-                    collection_name=self.collection_name,
-                    points_selector=Filter(
-                        must=[
-                            FieldCondition(key="full_path", match=MatchValue(value=display_path)),
-                            FieldCondition(key="org_id", match=MatchValue(value=org_id))
-                        ]
-                    )
-                )
-
-                # Delete from Tantivy
-                if self.tantivy_client:
-                    for cid in chunk_ids:
-                        try:
-                            self.tantivy_client.delete(cid)
-                        except Exception as e:
-                            logger.warning(f"Failed to delete chunk {cid} from Tantivy: {e}")
+            removed = self.delete_file(display_path, org_id)["chunks_removed"]
+            if removed:
+                logger.info(f"Deleted {removed} existing chunks for {display_path}")
         except Exception as e:
             logger.warning(f"Error checking/deleting existing chunks: {e}")
 
@@ -305,9 +275,10 @@ class Indexer:
         chunk_ids = []
         
         for i, chunk in enumerate(chunks):
-            # Deterministic chunk ID
+            # Deterministic chunk ID. Keyed on the upload name rather than
+            # file_path, which is a fresh temp file for every upload.
             content_hash = hashlib.sha256(
-                f"{file_path}:{chunk['chunk_index']}:{chunk['content']}".encode()
+                f"{display_path}:{chunk['chunk_index']}:{chunk['content']}".encode()
             ).hexdigest()
             chunk_id = str(uuid.UUID(content_hash[:32]))
             chunk_ids.append(chunk_id)
@@ -358,8 +329,15 @@ class Indexer:
         if self.tantivy_client:
             logger.info("Indexing in Tantivy (BM25)...")
             try:
+                # Path and lines let the BM25 service answer per-file lookups itself
                 tantivy_chunks = [
-                    (chunk_ids[i], chunks[i]["content"])
+                    (chunk_ids[i], chunks[i]["content"], {
+                        "store": org_id,
+                        "path": display_path,
+                        "language": chunks[i]["metadata"].get("language"),
+                        "start_line": chunks[i]["metadata"].get("start_line"),
+                        "end_line": chunks[i]["metadata"].get("end_line"),
+                    })
                     for i in range(len(chunks))
                 ]
                 if self.tantivy_client.batch_index(tantivy_chunks):
@@ -381,6 +359,156 @@ class Indexer:
             }
         }
     
+    def _file_filter(self, display_path: str, org_id: str):
+        """Match every chunk uploaded under `display_path` within `org_id`."""
+        from qdrant_client.models import Filter, FieldCondition, MatchValue
+
+        return Filter(
+            must=[
+                FieldCondition(key="full_path", match=MatchValue(value=display_path)),
+                FieldCondition(key="org_id", match=MatchValue(value=org_id))
+            ]
+        )
+
+    def _org_filter(self, org_id: str):
+        """Match every chunk within `org_id`."""
+        from qdrant_client.models import Filter, FieldCondition, MatchValue
+
+        return Filter(
+            must=[FieldCondition(key="org_id", match=MatchValue(value=org_id))]
+        )
+
+    def _scroll_all(self, scroll_filter, with_payload=False, page_size: int = 1000):
+        """Yield every point matching `scroll_filter`, following scroll pages."""
+        offset = None
+        while True:
+            points, offset = self.qdrant.scroll(
+                collection_name=self.collection_name,
+                scroll_filter=scroll_filter,
+                limit=page_size,
+                offset=offset,
+                with_payload=with_payload,
+                with_vectors=False
+            )
+            yield from points
+            if offset is None:
+                break
+
+    def file_chunks(self, display_path: str, org_id: str) -> List[Dict]:
+        """
+        List the chunks stored for an uploaded file, in line order.
+
+        Args:
+            display_path: Path the file was uploaded under (`full_path` payload)
+            org_id: Organization ID
+
+        Returns:
+            One dict per chunk with its id, position and line range
+        """
+        if not self.qdrant.collection_exists(self.collection_name):
+            return []
+
+        fields = ["chunk_index", "start_line", "end_line", "language", "chunk_type"]
+        chunks = []
+        for point in self._scroll_all(self._file_filter(display_path, org_id), fields):
+            payload = point.payload or {}
+            chunks.append({
+                "chunk_id": str(point.id),
+                "chunk_index": payload.get("chunk_index"),
+                "start_line": payload.get("start_line") or 0,
+                "end_line": payload.get("end_line") or 0,
+                "language": payload.get("language"),
+                "chunk_type": payload.get("chunk_type"),
+            })
+
+        chunks.sort(key=lambda c: (c["start_line"], c["chunk_index"] or 0))
+        return chunks
+
+    def stats(self, org_ids: List[str]) -> Dict:
+        """
+        Count indexed chunks, overall and for each of `org_ids`.
+
+        Counts are approximate, which keeps them cheap on large collections.
+        """
+        if not self.qdrant.collection_exists(self.collection_name):
+            return {"indexed_chunks": 0, "stores": {org_id: 0 for org_id in org_ids}}
+
+        total = self.qdrant.count(collection_name=self.collection_name, exact=False)
+        stores = {
+            org_id: self.qdrant.count(
+                collection_name=self.collection_name,
+                count_filter=self._org_filter(org_id),
+                exact=False
+            ).count
+            for org_id in org_ids
+        }
+        return {"indexed_chunks": total.count, "stores": stores}
+
+    def delete_file(self, display_path: str, org_id: str) -> Dict:
+        """
+        Delete every chunk of an uploaded file from Qdrant and Tantivy.
+
+        Args:
+            display_path: Path the file was uploaded under (`full_path` payload)
+            org_id: Organization ID
+
+        Returns:
+            Dict with status ("deleted" or "not_found") and chunks removed
+        """
+        if not self.qdrant.collection_exists(self.collection_name):
+            return {"status": "not_found", "chunks_removed": 0}
+
+        file_filter = self._file_filter(display_path, org_id)
+        chunk_ids = [str(p.id) for p in self._scroll_all(file_filter)]
+        if not chunk_ids:
+            return {"status": "not_found", "chunks_removed": 0}
+
+        self.qdrant.delete(
+            collection_name=self.collection_name,
+            points_selector=file_filter
+        )
+        self._delete_from_tantivy(chunk_ids)
+
+        return {"status": "deleted", "chunks_removed": len(chunk_ids)}
+
+    def clear_org(self, org_id: str) -> Dict:
+        """
+        Delete everything indexed for an org from Qdrant and Tantivy.
+
+        BM25 data goes first: if that fails nothing is removed from Qdrant,
+        so the clear can simply be retried.
+
+        Raises:
+            RuntimeError: If the BM25 index could not be cleared
+        """
+        if self.tantivy_client and not self.tantivy_client.clear(store=org_id):
+            raise RuntimeError(f"Failed to clear BM25 index for org '{org_id}'")
+
+        if not self.qdrant.collection_exists(self.collection_name):
+            return {"status": "cleared", "chunks_removed": 0}
+
+        org_filter = self._org_filter(org_id)
+        removed = self.qdrant.count(
+            collection_name=self.collection_name,
+            count_filter=org_filter,
+            exact=True
+        ).count
+        self.qdrant.delete(
+            collection_name=self.collection_name,
+            points_selector=org_filter
+        )
+
+        return {"status": "cleared", "chunks_removed": removed}
+
+    def _delete_from_tantivy(self, chunk_ids: List[str]):
+        """Remove chunks from the BM25 index; failures are logged, not raised."""
+        if not self.tantivy_client:
+            return
+        for start in range(0, len(chunk_ids), TANTIVY_DELETE_BATCH):
+            batch = chunk_ids[start:start + TANTIVY_DELETE_BATCH]
+            if not self.tantivy_client.delete_batch(batch):
+                logger.warning(f"Failed to delete {len(batch)} chunks from Tantivy")
+
     def delete_document(self, doc_id: str) -> Dict:
         """Delete all chunks for a document."""
         from qdrant_client.models import Filter, FieldCondition, MatchValue
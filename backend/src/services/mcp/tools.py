@@ -19,6 +19,9 @@ logger = logging.getLogger(__name__)
 
 COLLECTION_NAME = "rice_chunks"
 
+# Points fetched per scroll request when listing files
+LIST_FILES_PAGE_SIZE = 1000
+
 
 async def handle_search(
     query: str,
@@ -143,30 +146,33 @@ async def handle_list_files(
             ]
         )
         
-        # Scroll through all points
-        results = qdrant.scroll(
-            collection_name=COLLECTION_NAME,
-            scroll_filter=query_filter,
-            limit=10000,
-            with_payload=["file_path"],
-            with_vectors=False
-        )
-        
-        if not results or not results[0]:
-            return []
-        
-        # Extract unique file paths
+        # Scroll through all points, a page at a time
         file_paths = set()
-        for point in results[0]:
-            path = point.payload.get("file_path")
-            if path:
-                # Apply pattern filter if provided
-                if pattern:
-                    import fnmatch
-                    if fnmatch.fnmatch(path, pattern):
+        offset = None
+        while True:
+            points, offset = qdrant.scroll(
+                collection_name=COLLECTION_NAME,
+                scroll_filter=query_filter,
+                limit=LIST_FILES_PAGE_SIZE,
+                offset=offset,
+                with_payload=["file_path"],
+                with_vectors=False
+            )
+
+            # Extract unique file paths
+            for point in points:
+                path = point.payload.get("file_path")
+                if path:
+                    # Apply pattern filter if provided
+                    if pattern:
+                        import fnmatch
+                        if fnmatch.fnmatch(path, pattern):
+                            file_paths.add(path)
+                    else:
                         file_paths.add(path)
-                else:
-                    file_paths.add(path)
+
+            if offset is None:
+                break
         
         return sorted(list(file_paths))
         
@@ -31,6 +31,7 @@ class TantivyClient:
     - POST /index/batch - Batch index chunks
     - POST /search - BM25 search
     - DELETE /index/{chunk_id} - Delete chunk
+    - POST /index/delete/batch - Delete chunks by id
     - POST /index/clear - Clear index
     - GET /health - Health check
     """
@@ -82,23 +83,28 @@ class TantivyClient:
             logger.error(f"Failed to index chunk {chunk_id}: {e}")
             return False
     
-    def batch_index(self, chunks: List[Tuple[str, str]]) -> bool:
+    def batch_index(self, chunks: List[Tuple]) -> bool:
         """
         Index multiple chunks in a batch.
         
         Args:
-            chunks: List of (chunk_id, text) tuples
+            chunks: List of (chunk_id, text) tuples, or (chunk_id, text, metadata)
+                where metadata may set store, path, language, start_line and
+                end_line; unset (None) values are left out
             
         Returns:
             True if successful
         """
         try:
-            payload = {
-                "chunks": [
-                    {"chunk_id": cid, "text": text}
-                    for cid, text in chunks
-                ]
-            }
+            payload = {"chunks": []}
+            for chunk in chunks:
+                cid, text = chunk[0], chunk[1]
+                metadata = chunk[2] if len(chunk) > 2 else {}
+                payload["chunks"].append({
+                    "chunk_id": cid,
+                    "text": text,
+                    **{k: v for k, v in metadata.items() if v is not None},
+                })
             response = self.client.post("/index/batch", json=payload)
             response.raise_for_status()
             return True
@@ -145,10 +151,24 @@ class TantivyClient:
             logger.error(f"Failed to delete chunk {chunk_id}: {e}")
             return False
     
-    def clear(self) -> bool:
-        """Clear the entire index."""
+    def delete_batch(self, chunk_ids: List[str]) -> bool:
+        """Delete several chunks in one request."""
         try:
-            response = self.client.post("/index/clear")
+            response = self.client.post(
+                "/index/delete/batch",
+                json={"chunk_ids": chunk_ids}
+            )
+            response.raise_for_status()
+            return True
+        except Exception as e:
+            logger.error(f"Failed to delete {len(chunk_ids)} chunks: {e}")
+            return False
+    
+    def clear(self, store: Optional[str] = None) -> bool:
+        """Clear the entire index, or only the chunks of one store."""
+        try:
+            params = {"store": store} if store is not None else None
+            response = self.client.post("/index/clear", params=params)
             response.raise_for_status()
             return True
         except Exception as e:
@@ -1,9 +1,32 @@
 """
 Integration tests for Files API.
 """
+import asyncio
 import pytest
+from types import SimpleNamespace
+from unittest.mock import MagicMock, patch
 from fastapi.testclient import TestClient
 
+
+def test_list_files_follows_scroll_pages():
+    """Every page of points is read, not just the first."""
+    from src.services.mcp.tools import handle_list_files
+
+    def point(path):
+        return SimpleNamespace(payload={"file_path": path})
+
+    qdrant = MagicMock()
+    qdrant.scroll.side_effect = [
+        ([point("/repo/a.py"), point("/repo/b.py")], "page-2"),
+        ([point("/repo/a.py"), point("/repo/c.py")], None),
+    ]
+    with patch("src.services.mcp.tools.get_qdrant_client", return_value=qdrant):
+        files = asyncio.run(handle_list_files(org_id="acme"))
+
+    assert files == ["/repo/a.py", "/repo/b.py", "/repo/c.py"]
+    assert qdrant.scroll.call_args_list[1].kwargs["offset"] == "page-2"
+
+
 @pytest.mark.integration
 class TestFilesAPI:
     """Test file management endpoints."""
@@ -0,0 +1,295 @@
+"""
+Tests for per-file index endpoints and the Indexer lookups behind them.
+"""
+import uuid
+import pytest
+from types import SimpleNamespace
+from unittest.mock import MagicMock, patch
+
+from src.services.ingestion.chunker import DocumentChunker
+from src.services.ingestion.indexer import Indexer
+
+
+def chunk_id(path: str, index: int, content: str) -> str:
+    """Chunk ids as the indexer derives them."""
+    import hashlib
+    digest = hashlib.sha256(f"{path}:{index}:{content}".encode()).hexdigest()
+    return str(uuid.UUID(digest[:32]))
+
+
+def point(path: str, index: int, start_line: int, end_line: int):
+    return SimpleNamespace(
+        id=chunk_id(path, index, f"chunk {index}"),
+        payload={"chunk_index": index, "start_line": start_line, "end_line": end_line},
+    )
+
+
+def make_indexer(pages):
+    """An Indexer over a mock Qdrant that serves `pages` of scroll results."""
+    qdrant = MagicMock()
+    qdrant.collection_exists.return_value = True
+    qdrant.scroll.side_effect = [
+        (points, f"page-{n + 1}" if n + 1 < len(pages) else None)
+        for n, points in enumerate(pages)
+    ]
+    return Indexer(qdrant), qdrant
+
+
+def test_file_chunks_follows_scroll_pages_in_line_order():
+    path = "/repo/src/main.py"
+    second, first, third = point(path, 1, 41, 80), point(path, 0, 1, 40), point(path, 2, 81, 90)
+    indexer, qdrant = make_indexer([[second, first], [third]])
+
+    chunks = indexer.file_chunks(path, "acme")
+
+    assert [c["chunk_id"] for c in chunks] == [first.id, second.id, third.id]
+    assert (chunks[1]["start_line"], chunks[1]["end_line"]) == (41, 80)
+    # Ids are UUIDs, not path-derived
+    assert all(uuid.UUID(c["chunk_id"]) for c in chunks)
+
+    assert qdrant.scroll.call_count == 2
+    assert qdrant.scroll.call_args_list[1].kwargs["offset"] == "page-1"
+    conditions = qdrant.scroll.call_args_list[0].kwargs["scroll_filter"].must
+    assert {(c.key, c.match.value) for c in conditions} == {
+        ("full_path", path),
+        ("org_id", "acme"),
+    }
+
+
+def test_file_chunks_without_collection_is_empty():
+    indexer, qdrant = make_indexer([])
+    qdrant.collection_exists.return_value = False
+
+    assert indexer.file_chunks("/repo/a.py", "public") == []
+    qdrant.scroll.assert_not_called()
+
+
+def test_delete_file_removes_chunks_from_qdrant_and_tantivy():
+    path = "/repo/src/main.py"
+    points = [point(path, 0, 1, 40), point(path, 1, 41, 80)]
+    indexer, qdrant = make_indexer([points])
+    indexer._tantivy_client = MagicMock()
+
+    result = indexer.delete_file(path, "acme")
+
+    assert result == {"status": "deleted", "chunks_removed": 2}
+    selector = qdrant.delete.call_args.kwargs["points_selector"]
+    assert {(c.key, c.match.value) for c in selector.must} == {
+        ("full_path", path),
+        ("org_id", "acme"),
+    }
+    indexer._tantivy_client.delete_batch.assert_called_once_with([p.id for p in points])
+
+
+def test_delete_file_not_indexed():
+    indexer, qdrant = make_indexer([[]])
+    indexer._tantivy_client = MagicMock()
+
+    assert indexer.delete_file("/repo/gone.py", "acme") == {
+        "status": "not_found",
+        "chunks_removed": 0,
+    }
+    qdrant.delete.assert_not_called()
+    indexer._tantivy_client.delete_batch.assert_not_called()
+
+
+def test_stats_counts_chunks_per_store():
+    indexer, qdrant = make_indexer([])
+    totals = {None: 12, "public": 5, "acme": 7}
+    qdrant.count.side_effect = lambda collection_name, count_filter=None, exact=True: (
+        SimpleNamespace(
+            count=totals[count_filter.must[0].match.value if count_filter else None]
+        )
+    )
+
+    assert indexer.stats(["acme", "public"]) == {
+        "indexed_chunks": 12,
+        "stores": {"acme": 7, "public": 5},
+    }
+
+
+def test_stats_without_collection_is_zero():
+    indexer, qdrant = make_indexer([])
+    qdrant.collection_exists.return_value = False
+
+    assert indexer.stats(["public"]) == {"indexed_chunks": 0, "stores": {"public": 0}}
+    qdrant.count.assert_not_called()
+
+
+def test_clear_org_removes_bm25_then_vectors():
+    indexer, qdrant = make_indexer([])
+    qdrant.count.return_value = SimpleNamespace(count=4)
+    indexer._tantivy_client = MagicMock()
+    indexer._tantivy_client.clear.return_value = True
+
+    assert indexer.clear_org("acme") == {"status": "cleared", "chunks_removed": 4}
+    indexer._tantivy_client.clear.assert_called_once_with(store="acme")
+    selector = qdrant.delete.call_args.kwargs["points_selector"]
+    assert [(c.key, c.match.value) for c in selector.must] == [("org_id", "acme")]
+
+
+def test_clear_org_keeps_vectors_when_bm25_fails():
+    indexer, qdrant = make_indexer([])
+    indexer._tantivy_client = MagicMock()
+    indexer._tantivy_client.clear.return_value = False
+
+    with pytest.raises(RuntimeError):
+        indexer.clear_org("acme")
+    qdrant.delete.assert_not_called()
+
+
+# Lines of 19 characters: with chunk_size 45, two lines fit in a chunk
+LINES = [f"line {n:02d} of the text" for n in range(8)]
+
+
+def ingested_chunks(lines, upload_path="/tmp/ingest/upload-1.txt"):
+    """(chunk_id, content) pairs ingest_file stores for `lines` uploaded as /repo/notes.txt."""
+    indexer = Indexer(MagicMock())
+    indexer.chunker = DocumentChunker(chunk_size=45, chunk_overlap=0)
+    indexer.delete_file = MagicMock(return_value={"chunks_removed": 0})
+
+    with patch("src.services.ingestion.indexer.get_ast_parser") as ast_parser, \
+            patch("src.services.ingestion.indexer.DocumentParser.parse_file",
+                  return_value="\n".join(lines)), \
+            patch("src.services.ingestion.indexer.embed_texts",
+                  side_effect=lambda texts: [[0.0]] * len(texts)), \
+            patch.object(Indexer, "splade_encoder", None), \
+            patch.object(Indexer, "bm42_encoder", None), \
+            patch.object(Indexer, "tantivy_client", None):
+        ast_parser.return_value.can_parse.return_value = False
+        result = indexer.ingest_file(upload_path, "/repo/notes.txt", "repo", "acme")
+
+    assert result["status"] == "success"
+    points = indexer.qdrant.upsert.call_args.kwargs["points"]
+    return [(p.id, p.payload["text"]) for p in points]
+
+
+def test_chunk_ids_are_stable_across_uploads():
+    first = ingested_chunks(LINES, upload_path="/tmp/ingest/upload-1.txt")
+    second = ingested_chunks(LINES, upload_path="/tmp/ingest/upload-2.txt")
+
+    assert len(first) == 4
+    assert first == second
+    # Derived from the upload name, chunk index and content only
+    assert [cid for cid, _ in first] == [
+        chunk_id("/repo/notes.txt", i, content) for i, (_, content) in enumerate(first)
+    ]
+
+
+def test_editing_a_line_changes_only_its_chunk_id():
+    edited = list(LINES)
+    edited[5] = "line 05 of the TEXT"  # same length, so chunk boundaries hold
+
+    before = [cid for cid, _ in ingested_chunks(LINES)]
+    after = [cid for cid, _ in ingested_chunks(edited)]
+
+    assert [i for i in range(len(before)) if before[i] != after[i]] == [2]
+
+
+def test_shifting_chunk_boundaries_changes_every_later_id():
+    # Ids include the chunk index, so a line that gets a chunk of its own
+    # gives every later chunk a new id, even though their text is unchanged
+    inserted = LINES[:2] + ["an inserted line too long to share a chunk"] + LINES[2:]
+
+    before = ingested_chunks(LINES)
+    after = ingested_chunks(inserted)
+
+    assert len(after) == 5
+    assert after[0] == before[0]
+    assert [text for _, text in after[2:]] == [text for _, text in before[1:]]
+    assert not {cid for cid, _ in after[1:]} & {cid for cid, _ in before}
+
+
+@pytest.mark.integration
+class TestIndexAPI:
+    """Test per-file index endpoints."""
+
+    def test_file_chunks(self, api_client):
+        from src.main import app
+        from src.api.v1.endpoints.index import get_indexer
+
+        path = "/repo/src/main.py"
+        indexer, _ = make_indexer([[point(path, 0, 1, 40)]])
+        app.dependency_overrides[get_indexer] = lambda: indexer
+        try:
+            response = api_client.get(
+                "/api/v1/index/chunks", params={"path": path, "org_id": "acme"}
+            )
+            assert response.status_code == 200, response.text
+            data = response.json()
+            assert data["path"] == path
+            assert data["total"] == 1
+            assert data["chunks"][0]["chunk_id"] == chunk_id(path, 0, "chunk 0")
+        finally:
+            del app.dependency_overrides[get_indexer]
+
+    def test_file_chunks_requires_path(self, api_client):
+        response = api_client.get("/api/v1/index/chunks")
+        assert response.status_code == 422
+
+    def test_delete_file(self, api_client):
+        from src.main import app
+        from src.api.v1.dependencies import verify_admin
+        from src.api.v1.endpoints.index import get_indexer
+
+        path = "/repo/src/main.py"
+        indexer, _ = make_indexer([[point(path, 0, 1, 40)]])
+        indexer._tantivy_client = MagicMock()
+        app.dependency_overrides[verify_admin] = lambda: {"org_id": "acme"}
+        app.dependency_overrides[get_indexer] = lambda: indexer
+        try:
+            response = api_client.delete(
+                "/api/v1/index/file", params={"path": path, "org_id": "acme"}
+            )
+            assert response.status_code == 200, response.text
+            data = response.json()
+            assert data["status"] == "deleted"
+            assert data["chunks_removed"] == 1
+        finally:
+            del app.dependency_overrides[verify_admin]
+            del app.dependency_overrides[get_indexer]
+
+    def test_stats(self, api_client):
+        from src.main import app
+        from src.api.v1.endpoints.index import get_indexer, get_store_ids
+
+        indexer, qdrant = make_indexer([])
+        qdrant.count.return_value = SimpleNamespace(count=3)
+        indexer._tantivy_client = MagicMock()
+        indexer._tantivy_client.health.return_value = {"status": "healthy", "indexed_docs": 3}
+        app.dependency_overrides[get_indexer] = lambda: indexer
+        app.dependency_overrides[get_store_ids] = lambda: ["public"]
+        try:
+            response = api_client.get("/api/v1/index/stats")
+            assert response.status_code == 200, response.text
+            data = response.json()
+            assert data["status"] == "ok"
+            assert data["indexed_chunks"] == 3
+            assert data["stores"] == {"public": 3}
+            assert data["bm25"]["indexed_docs"] == 3
+        finally:
+            del app.dependency_overrides[get_indexer]
+            del app.dependency_overrides[get_store_ids]
+
+    def test_clear_index(self, api_client):
+        from src.main import app
+        from src.api.v1.dependencies import verify_admin
+        from src.api.v1.endpoints.index import get_indexer
+
+        indexer, qdrant = make_indexer([])
+        qdrant.count.return_value = SimpleNamespace(count=2)
+        indexer._tantivy_client = MagicMock()
+        indexer._tantivy_client.clear.return_value = True
+        app.dependency_overrides[verify_admin] = lambda: {"org_id": "acme"}
+        app.dependency_overrides[get_indexer] = lambda: indexer
+        try:
+            response = api_client.post("/api/v1/index/clear", params={"org_id": "acme"})
+            assert response.status_code == 200, response.text
+            assert response.json() == {"status": "cleared", "org_id": "acme", "chunks_removed": 2}
+
+            indexer._tantivy_client.clear.return_value = False
+            response = api_client.post("/api/v1/index/clear", params={"org_id": "acme"})
+            assert response.status_code == 503
+        finally:
+            del app.dependency_overrides[verify_admin]
+            del app.dependency_overrides[get_indexer]
@@ -0,0 +1,48 @@
+use crate::core::api::ApiClient;
+use crate::core::config::load_config;
+use crate::core::manifest::{manifest_path, Manifest};
+use anyhow::Result;
+use colored::*;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+pub async fn run(org_id: Option<&str>, yes: bool) -> Result<()> {
+    let config = load_config()?;
+    let org_id = &config.org_id(org_id);
+    if !yes && !confirm(org_id)? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let client = ApiClient::from_config(&config);
+    let removed = client.clear(org_id).await?;
+
+    // Only now that the backend confirmed the clear: otherwise the next scan
+    // would skip every file it uploaded before. The manifest isn't kept per
+    // org, so files of other orgs are simply uploaded again on their next scan.
+    let mut manifest = Manifest::load(&manifest_path());
+    manifest.clear();
+    if let Err(e) = manifest.save() {
+        log::warn!("Could not reset manifest: {}", e);
+    }
+
+    println!("{} Cleared index for org '{}' ({} chunks)", "✓".green(), org_id, removed);
+    Ok(())
+}
+
+/// Ask before deleting anything; without a terminal to ask on, require `--yes`
+fn confirm(org_id: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!("Refusing to clear the index without confirmation; pass --yes");
+    }
+
+    print!(
+        "{} This deletes everything indexed for org '{}'. Continue? [y/N] ",
+        "Warning:".yellow(),
+        org_id
+    );
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
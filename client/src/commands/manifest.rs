@@ -0,0 +1,21 @@
+use crate::core::manifest::{manifest_path, Manifest};
+use anyhow::Result;
+use colored::*;
+
+/// Print every file recorded in the manifest with its hash
+pub fn show(json: bool) -> Result<()> {
+    let path = manifest_path();
+    let manifest = Manifest::load(&path);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(manifest.entries())?);
+        return Ok(());
+    }
+
+    println!("{} {} ({} files)", "Manifest:".bold(), path.display(), manifest.entries().len());
+    for (file, entry) in manifest.entries() {
+        let hash = entry.hash.get(..12).unwrap_or(&entry.hash);
+        println!("  {}  {}", hash.dimmed(), file);
+    }
+    Ok(())
+}
@@ -1,2 +1,6 @@
+pub mod clear;
+pub mod manifest;
+pub mod reindex;
 pub mod search;
+pub mod status;
 pub mod watch;
@@ -0,0 +1,35 @@
+use crate::core::api::ApiClient;
+use crate::core::config::{global_ignore_path, load_config};
+use crate::core::manifest::{manifest_path, Manifest};
+use crate::watcher::filter::FileFilter;
+use crate::watcher::progress::Progress;
+use crate::watcher::scanner::Scanner;
+use anyhow::Result;
+use std::path::Path;
+
+/// How a reindex walks the tree and reports on it
+#[derive(Debug, Clone, Copy)]
+pub struct ReindexOptions {
+    /// Only report what would be uploaded and deleted
+    pub dry_run: bool,
+    pub follow_symlinks: bool,
+    pub concurrency: usize,
+    pub progress: Progress,
+}
+
+/// Reconcile what is indexed for `path` in the org with the files on disk
+pub async fn run(path: &str, org_id: Option<&str>, options: ReindexOptions) -> Result<()> {
+    let config = load_config()?;
+    let client = ApiClient::from_config(&config);
+    let manifest = Manifest::load(&manifest_path()).shared();
+    let scanner = Scanner::new(client, config.org_id(org_id), manifest)
+        .with_concurrency(options.concurrency)
+        .with_dry_run(options.dry_run)
+        .with_follow_symlinks(options.follow_symlinks)
+        .with_progress(options.progress)
+        .with_global_ignore(Some(global_ignore_path()))
+        .with_filter(FileFilter::from_config(&config));
+
+    scanner.reconcile(Path::new(path)).await?;
+    Ok(())
+}
@@ -1,19 +1,80 @@
-use crate::core::api::ApiClient;
+use crate::core::api::{ApiClient, BackendError, FailureKind};
 use crate::core::config::load_config;
 use anyhow::Result;
 use colored::*;
 
-pub async fn run(query: &str, limit: usize, json: bool) -> Result<()> {
+/// What the user can do about a backend failure
+fn hint(err: &BackendError) -> String {
+    match err.kind {
+        FailureKind::ConnectionRefused | FailureKind::Unreachable => format!(
+            "Start the backend (`make up` in the rice-search checkout), or point the client \
+             at it with `ricesearch config set backend_url <url>` or RICE_BACKEND_URL \
+             (currently {})",
+            err.url
+        ),
+        FailureKind::Timeout => "The backend is reachable but slow; check `make api-logs`, or \
+             raise `ricesearch config set timeout_secs <secs>`"
+            .to_string(),
+        FailureKind::ClientError => format!(
+            "The backend rejected the request; check that {} is the rice-search API",
+            err.url
+        ),
+        FailureKind::ServerError => {
+            "The backend failed to handle the request; check `make api-logs`".to_string()
+        }
+    }
+}
+
+/// Report a backend failure and exit non-zero
+///
+/// With `--json` the failure is a JSON object on stdout so scripts can detect it.
+fn fail(err: &BackendError, json: bool) -> ! {
+    if json {
+        let mut error = serde_json::to_value(err).expect("backend errors always serialize");
+        error["hint"] = hint(err).into();
+        let output = serde_json::json!({ "error": error });
+        println!("{}", serde_json::to_string_pretty(&output).unwrap_or_default());
+    } else {
+        eprintln!("{} {}", "Error:".red().bold(), err);
+        eprintln!("{} {}", "Hint:".yellow(), hint(err));
+    }
+    std::process::exit(1);
+}
+
+pub async fn run(
+    query: &str,
+    limit: usize,
+    json: bool,
+    org_id: Option<&str>,
+    tokenizer: Option<&str>,
+) -> Result<()> {
     let config = load_config()?;
-    let client = ApiClient::new(&config.backend_url);
+    let client = ApiClient::from_config(&config);
+    let org_id = &config.org_id(org_id);
 
-    let result = client.search(query, limit, true).await?;
+    // Fail fast with a clear message rather than after a round of search retries
+    if let Err(err) = client.ping().await {
+        fail(&err, json);
+    }
+    if let Some(tokenizer) = tokenizer {
+        client.require_tokenizer(tokenizer).await?;
+    }
+
+    let result = match client.search(query, limit, true, org_id).await {
+        Ok(result) => result,
+        Err(err) => match err.downcast_ref::<BackendError>() {
+            Some(backend_error) => fail(backend_error, json),
+            None => return Err(err),
+        },
+    };
 
     if json {
         println!("{}", serde_json::to_string_pretty(&result)?);
         return Ok(());
     }
 
+    println!("Searching {} in org {}\n", query.bold(), org_id.cyan());
+
     // Pretty Print
     if let Some(results) = result.get("results").and_then(|v| v.as_array()) {
         if results.is_empty() {
@@ -26,7 +87,12 @@ pub async fn run(query: &str, limit: usize, json: bool) -> Result<()> {
                 .get("path")
                 .and_then(|s| s.as_str())
                 .unwrap_or("unknown");
-            let line = item.get("start_line").and_then(|n| n.as_u64()).unwrap_or(0);
+            // Jump to the matched line when the backend reports one
+            let line = item
+                .get("match_line")
+                .or_else(|| item.get("start_line"))
+                .and_then(|n| n.as_u64())
+                .unwrap_or(0);
             let snippet = item.get("content").and_then(|s| s.as_str()).unwrap_or("");
             let score = item.get("score").and_then(|f| f.as_f64()).unwrap_or(0.0);
 
@@ -42,6 +108,18 @@ pub async fn run(query: &str, limit: usize, json: bool) -> Result<()> {
             }
             println!();
         }
+
+        // Prefer the backend's true match count over the page size
+        let returned = result
+            .get("returned")
+            .and_then(|n| n.as_u64())
+            .unwrap_or(results.len() as u64);
+        let total = result
+            .get("total_hits")
+            .or_else(|| result.get("total"))
+            .and_then(|n| n.as_u64())
+            .unwrap_or(returned);
+        println!("Showing {} of {} results", returned, total);
     } else {
         println!("Invalid response format.");
     }
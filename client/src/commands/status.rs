@@ -0,0 +1,100 @@
+use crate::core::api::ApiClient;
+use crate::core::config::load_config;
+use crate::core::paths::upload_name;
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+/// Show what is indexed for `path`, or overall backend index stats without one
+pub async fn run(path: Option<&str>, org_id: Option<&str>, json: bool) -> Result<()> {
+    let config = load_config()?;
+    let client = ApiClient::from_config(&config);
+
+    match path {
+        Some(path) => file_status(&client, path, &config.org_id(org_id), json).await,
+        None => index_stats(&client, &config.backend_url, json).await,
+    }
+}
+
+async fn index_stats(client: &ApiClient, backend_url: &str, json: bool) -> Result<()> {
+    let stats = client.stats().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    let status = stats.get("status").and_then(|s| s.as_str()).unwrap_or("unknown");
+    let chunks = stats.get("indexed_chunks").and_then(|n| n.as_u64()).unwrap_or(0);
+
+    println!("{} {}", "Backend:".bold(), backend_url);
+    let status = if status == "ok" { status.green() } else { status.yellow() };
+    println!("  {:<14}{}", "status", status);
+    println!("  {:<14}{}", "chunks", chunks.to_string().bold());
+
+    // BM25 index health as reported by the search service
+    if let Some(bm25) = stats.get("bm25") {
+        let bm25_status = bm25.get("status").and_then(|s| s.as_str()).unwrap_or("unknown");
+        let bm25_status =
+            if bm25_status == "healthy" { bm25_status.green() } else { bm25_status.yellow() };
+        match bm25.get("indexed_docs").and_then(|n| n.as_u64()) {
+            Some(docs) => println!("  {:<14}{} ({} docs)", "bm25", bm25_status, docs),
+            None => println!("  {:<14}{}", "bm25", bm25_status),
+        }
+        if let Some(tokenizer) = bm25.get("tokenizer").and_then(|t| t.as_str()) {
+            println!("  {:<14}{}", "tokenizer", tokenizer);
+        }
+    }
+
+    if let Some(stores) = stats.get("stores").and_then(|v| v.as_object()) {
+        println!("{}", "Stores:".bold());
+        for (store, count) in stores {
+            println!("  {:<14}{}", store.magenta(), count.as_u64().unwrap_or(0));
+        }
+    }
+
+    Ok(())
+}
+
+async fn file_status(client: &ApiClient, path: &str, org_id: &str, json: bool) -> Result<()> {
+    // Look the file up under the same name the scanner and watcher index it as
+    let name = upload_name(Path::new(path));
+    let result = client.file_chunks(&name, org_id).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+
+    let chunks = result
+        .get("chunks")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    if chunks.is_empty() {
+        println!("{} {} is not indexed.", "[NOT INDEXED]".yellow(), name);
+        return Ok(());
+    }
+
+    println!("{} {} ({} chunks)", "[INDEXED]".green(), name, chunks.len());
+    for chunk in chunks {
+        let chunk_id = chunk
+            .get("chunk_id")
+            .and_then(|s| s.as_str())
+            .unwrap_or("unknown");
+        let start = chunk.get("start_line").and_then(|n| n.as_u64()).unwrap_or(0);
+        let end = chunk.get("end_line").and_then(|n| n.as_u64()).unwrap_or(0);
+        let kind = chunk.get("chunk_type").and_then(|s| s.as_str()).unwrap_or("text");
+
+        println!(
+            "  {} lines {}-{} {}",
+            chunk_id.magenta(),
+            start.to_string().green(),
+            end.to_string().green(),
+            kind.dimmed()
+        );
+    }
+
+    Ok(())
+}
@@ -1,68 +1,131 @@
-use crate::core::api::ApiClient;
-use crate::core::config::load_config;
+use crate::core::api::{ApiClient, FileDeletion};
+use crate::core::config::{global_ignore_path, load_config};
+use crate::core::manifest::{manifest_path, Manifest, ManifestEntry, SharedManifest};
+use crate::core::paths::upload_name;
+use crate::watcher::filter::FileFilter;
+use crate::watcher::progress::{Progress, ProgressEvent};
 use crate::watcher::scanner::Scanner;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
-use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-const DEBOUNCE_DELAY: Duration = Duration::from_secs(3);
+/// How long a file must go unchanged before it is re-indexed
+pub const DEFAULT_DEBOUNCE_MS: u64 = 3000;
+
+/// How often pending files are checked against the debounce delay
+///
+/// A quarter of the debounce keeps short debounces responsive, while the
+/// bounds avoid busy-polling or needlessly long waits.
+fn poll_interval(debounce: Duration) -> Duration {
+    (debounce / 4).clamp(Duration::from_millis(10), Duration::from_millis(500))
+}
+
+/// How the initial scan before watching behaves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanOptions {
+    /// Index every file under the roots before watching
+    pub full_index: bool,
+    /// Only report what the full index would upload, then exit
+    pub dry_run: bool,
+    pub follow_symlinks: bool,
+    /// Format of the per-file lines printed while scanning and watching
+    pub progress: Progress,
+}
+
+pub async fn run(
+    paths: &[String],
+    org_id: Option<String>,
+    debounce: Duration,
+    options: ScanOptions,
+) -> Result<()> {
+    let ScanOptions { full_index, dry_run, follow_symlinks, progress } = options;
+    anyhow::ensure!(!debounce.is_zero(), "Debounce delay must be greater than zero");
 
-pub async fn run(path: &str, org_id: Option<String>, full_index: bool) -> Result<()> {
     let config = load_config()?;
-    let client = ApiClient::new(&config.backend_url);
+    let client = ApiClient::from_config(&config);
     
-    // Check health before starting
-    if !client.health_check().await {
-        eprintln!("{} Backend at {} seems down or unhealthy.", "Warning:".yellow(), config.backend_url);
-    } else {
-        println!("{} Backend connected successfully.", "✓".green());
+    // Check health before starting (a dry run never talks to the backend)
+    if !dry_run {
+        if !client.health_check().await {
+            eprintln!(
+                "{} Backend at {} seems down or unhealthy.",
+                "Warning:".yellow(),
+                config.backend_url
+            );
+        } else {
+            progress.status(&format!("{} Backend connected successfully.", "✓".green()));
+        }
     }
 
-    let oid = org_id.unwrap_or("public".to_string());
+    let oid = config.org_id(org_id.as_deref());
 
-    // Use the path as provided (relative like ".")
-    let root_path = Path::new(path);
-    
-    let scanner = Scanner::new(ApiClient::new(&config.backend_url), oid.clone());
+    let filter = FileFilter::from_config(&config);
+    let manifest = Manifest::load(&manifest_path()).shared();
+    let scanner = Scanner::new(client.clone(), oid.clone(), manifest)
+        .with_filter(filter.clone())
+        .with_dry_run(dry_run)
+        .with_follow_symlinks(follow_symlinks)
+        .with_progress(progress)
+        .with_global_ignore(Some(global_ignore_path()));
 
-    // Initial Scan
+    // Initial Scan, using the paths as provided (relative like ".") for display
     if full_index {
-        scanner.scan(root_path).await;
+        for path in paths {
+            scanner.scan(Path::new(path)).await;
+        }
+    }
+    if dry_run {
+        return Ok(());
     }
 
-    // Build Ignore Matcher from the root path (works with relative paths)
-    let mut builder = ignore::gitignore::GitignoreBuilder::new(root_path);
-    builder.add(root_path.join(".gitignore"));
-    builder.add(root_path.join(".riceignore"));
-    let ignore_matcher = builder.build().unwrap();
+    // Watch absolute roots so event paths (and the debounce map keyed on them)
+    // can't collide between roots, with one ignore matcher per root
+    let mut ignore_rules = Vec::with_capacity(paths.len());
+    for path in paths {
+        let root = std::fs::canonicalize(path)
+            .with_context(|| format!("Cannot watch {}", path))?;
+        ignore_rules.push(IgnoreRules::load(&root, Some(global_ignore_path()))?);
+    }
+    let mut roots = WatchRoots(ignore_rules);
 
-    println!("Starting watcher on: {} (debounce: {}s)", path, DEBOUNCE_DELAY.as_secs());
+    progress.status(&format!(
+        "Starting watcher on: {} (debounce: {}ms)",
+        paths.join(", "),
+        debounce.as_millis()
+    ));
 
     let (tx, rx) = channel();
 
     let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
 
-    // Watch the path as provided
-    watcher.watch(root_path, RecursiveMode::Recursive)?;
+    for rules in &roots.0 {
+        watcher.watch(&rules.root, RecursiveMode::Recursive)?;
+    }
 
     let rt = tokio::runtime::Handle::current();
     
+    // Uploads and deletes are recorded in the same manifest the scan used
+    let manifest = scanner.manifest();
+
     // Per-file debounce tracking: file_path -> (last_change_time, scheduled)
     let pending_files: Arc<Mutex<HashMap<PathBuf, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
     
     // Spawn debounce processor
     let pending_clone = pending_files.clone();
-    let config_clone = config.clone();
+    let client_clone = client.clone();
     let oid_clone = oid.clone();
+    let manifest_clone = manifest.clone();
     
     rt.spawn(async move {
         loop {
-            tokio::time::sleep(Duration::from_millis(500)).await;
+            tokio::time::sleep(poll_interval(debounce)).await;
             
             // Check for files ready to be indexed
             let files_ready: Vec<PathBuf> = {
@@ -70,7 +133,7 @@ pub async fn run(path: &str, org_id: Option<String>, full_index: bool) -> Result
                 let now = Instant::now();
                 let ready: Vec<PathBuf> = pending
                     .iter()
-                    .filter(|(_, last_change)| now.duration_since(**last_change) >= DEBOUNCE_DELAY)
+                    .filter(|(_, last_change)| now.duration_since(**last_change) >= debounce)
                     .map(|(path, _)| path.clone())
                     .collect();
                 
@@ -83,73 +146,409 @@ pub async fn run(path: &str, org_id: Option<String>, full_index: bool) -> Result
             
             // Index ready files
             for file_path in files_ready {
-                let c = ApiClient::new(&config_clone.backend_url);
+                let c = client_clone.clone();
                 let o = oid_clone.clone();
                 
                 let abs_path = std::fs::canonicalize(&file_path)
                     .unwrap_or_else(|_| file_path.clone());
+                let upload_name = upload_name(&abs_path);
+                let path = upload_name.as_str();
+
+                if let Some(reason) = filter.check(&abs_path) {
+                    progress.emit(&ProgressEvent::Skipped { path, reason: reason.to_string() });
+                    continue;
+                }
                 
-                let hash = crate::core::hashing::compute_file_hash(&abs_path)
-                    .unwrap_or_else(|_| "unknown".to_string());
-                
-                // Clean UNC prefix for server
-                let path_str = abs_path.to_string_lossy();
-                let clean_path = if path_str.starts_with("\\\\?\\") {
-                    &path_str[4..]
-                } else {
-                    &path_str
-                };
-                let upload_name = clean_path.replace("\\", "/");
-
-                println!("Indexing: {} (hash: {})", upload_name, &hash[..8]);
-                let _ = c.index_file(&abs_path, &upload_name, &o).await;
+                // An unreadable file still gets an upload attempt so the error is reported
+                let hash = crate::core::hashing::compute_file_hash(&abs_path).ok();
+                let short_hash = hash.as_deref().and_then(|hash| hash.get(..8));
+                log::debug!("{} has hash {}", upload_name, short_hash.unwrap_or("unknown"));
+
+                progress.emit(&ProgressEvent::Indexing { path });
+                match c.index_file(&abs_path, &upload_name, &o).await {
+                    Ok(_) => {
+                        let bytes = std::fs::metadata(&abs_path).map(|m| m.len()).unwrap_or(0);
+                        progress.emit(&ProgressEvent::Indexed { path, bytes });
+                        let mut manifest = manifest_clone.lock().unwrap();
+                        record_upload(&mut manifest, upload_name.clone(), hash);
+                    }
+                    Err(e) => progress.emit(&ProgressEvent::Error { path, error: e.to_string() }),
+                }
             }
         }
     });
 
+    // Queue a file for (re-)indexing once it settles (debounce)
+    let queue_index = |event_path: PathBuf| {
+        let mut pending = pending_files.lock().unwrap();
+        pending.insert(event_path, Instant::now());
+    };
+
+    // Queue every file under a directory that was moved into place
+    let queue_tree = |dir: &Path, roots: &WatchRoots| {
+        for file in files_under(dir) {
+            if !roots.is_ignored(&file) {
+                queue_index(file);
+            }
+        }
+    };
+
+    // Delete a path's chunks; the file is gone, so only its name is used.
+    // A removed or renamed directory deletes every file recorded under it.
+    let queue_delete = |event_path: &Path| {
+        // Drop any pending re-index of the removed file(s)
+        pending_files.lock().unwrap().retain(|pending, _| !pending.starts_with(event_path));
+
+        let name = upload_name(event_path);
+        let c = client.clone();
+        let o = oid.clone();
+        let manifest = manifest.clone();
+        rt.spawn(async move {
+            let targets = indexed_under(&manifest.lock().unwrap(), &name);
+            for path in &targets {
+                let path = path.as_str();
+                match delete_indexed_file(&c, &manifest, path, &o).await {
+                    Ok(FileDeletion::Deleted { .. }) => {
+                        progress.emit(&ProgressEvent::Deleted { path })
+                    }
+                    Ok(FileDeletion::NotIndexed) => progress.emit(&ProgressEvent::Skipped {
+                        path,
+                        reason: "not indexed".to_string(),
+                    }),
+                    Err(e) => progress.emit(&ProgressEvent::Error { path, error: e.to_string() }),
+                }
+            }
+        });
+    };
+
     for res in rx {
         match res {
             Ok(event) => {
+                // Edited ignore files take effect without restarting the watcher;
+                // the rules are only read here, so rebuilding can't race a lookup
+                for ignore_file in event.paths.iter().filter(|p| is_ignore_file(p)) {
+                    if let Some(rules) = roots.rules_for_mut(ignore_file) {
+                        let root = rules.root.display().to_string();
+                        match rules.reload() {
+                            Ok(()) => log::info!("Reloaded ignore rules for {}", root),
+                            Err(e) => log::warn!("Keeping previous ignore rules for {}: {}", root, e),
+                        }
+                    }
+                }
+
                 match event.kind {
-                    notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
-                        for event_path in event.paths {
-                            if !event_path.is_file() { continue; }
-
-                            // 1. Ignore .git explicitly
-                            if event_path.components().any(|c| c.as_os_str() == ".git") { continue; }
-
-                            // 2. Get relative path from the event path
-                            let cwd = std::env::current_dir().unwrap_or_default();
-                            let relative_path = event_path.strip_prefix(&cwd)
-                                .or_else(|_| event_path.strip_prefix(root_path))
-                                .unwrap_or(&event_path);
-                            
-                            // Normalize to forward slashes for gitignore matching
-                            let rel_str = relative_path.to_string_lossy().replace("\\", "/");
-                            
-                            // 3. Check gitignore
-                            let matched = ignore_matcher.matched_path_or_any_parents(&rel_str, false);
-                            
-                            match matched {
-                                ignore::Match::Ignore(_) => {
-                                    continue; 
-                                },
-                                _ => {}
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                        // paths are [from, to]
+                        if let [from, to] = event.paths.as_slice() {
+                            if !roots.is_ignored(from) {
+                                queue_delete(from);
+                            }
+                            if roots.is_ignored(to) {
+                                // Moved somewhere ignored: only the delete applies
+                            } else if to.is_file() {
+                                queue_index(to.clone());
+                            } else if to.is_dir() {
+                                queue_tree(to, &roots);
                             }
+                        }
+                    }
+                    EventKind::Modify(ModifyKind::Name(_)) => {
+                        // Only one side of the rename is known (platform-dependent):
+                        // re-index it if it still exists, otherwise treat it as removed
+                        for event_path in event.paths {
+                            if roots.is_ignored(&event_path) { continue; }
 
-                            // 4. Add/update to pending (debounce)
-                            {
-                                let mut pending = pending_files.lock().unwrap();
-                                pending.insert(event_path.clone(), Instant::now());
+                            if event_path.is_file() {
+                                queue_index(event_path);
+                            } else if event_path.is_dir() {
+                                queue_tree(&event_path, &roots);
+                            } else if !event_path.exists() {
+                                queue_delete(&event_path);
                             }
                         }
                     }
+                    EventKind::Create(_) | EventKind::Modify(_) => {
+                        for event_path in event.paths {
+                            if !event_path.is_file() || roots.is_ignored(&event_path) { continue; }
+                            queue_index(event_path);
+                        }
+                    }
+                    EventKind::Remove(_) => {
+                        for event_path in event.paths {
+                            // The file is gone, so its type can't be checked
+                            if roots.is_ignored(&event_path) { continue; }
+                            queue_delete(&event_path);
+                        }
+                    }
                     _ => (),
                 }
             }
-            Err(e) => println!("Watch error: {:?}", e),
+            Err(e) => progress.status(&format!("Watch error: {:?}", e)),
         }
     }
 
     Ok(())
 }
+
+/// Upload names to delete when `name` is removed or renamed away
+///
+/// The event doesn't say whether a directory or a file went, so every file the
+/// manifest records under `name` is included; otherwise `name` itself.
+fn indexed_under(manifest: &Manifest, name: &str) -> Vec<String> {
+    let prefix = format!("{}/", name.trim_end_matches('/'));
+    let under: Vec<String> = manifest
+        .entries()
+        .keys()
+        .filter(|file| *file == name || file.starts_with(&prefix))
+        .cloned()
+        .collect();
+    if under.is_empty() {
+        vec![name.to_string()]
+    } else {
+        under
+    }
+}
+
+/// Files below `dir`, which the per-root ignore rules are applied to afterwards
+fn files_under(dir: &Path) -> Vec<PathBuf> {
+    ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Delete the chunks a removed or renamed file produced and drop it from the manifest
+///
+/// The manifest entry is only dropped once the backend has answered; a failed
+/// delete leaves it so the next reconcile can retry.
+async fn delete_indexed_file(
+    client: &ApiClient,
+    manifest: &SharedManifest,
+    path: &str,
+    org_id: &str,
+) -> Result<FileDeletion> {
+    let deletion = client.delete_file(path, org_id).await?;
+
+    let mut manifest = manifest.lock().unwrap();
+    manifest.remove(path);
+    save_manifest(&mut manifest);
+    Ok(deletion)
+}
+
+/// Record a file the watcher uploaded
+///
+/// Without a hash the next scan couldn't tell whether the file changed, so,
+/// like the scanner, the upload is left unrecorded and the file is sent again.
+fn record_upload(manifest: &mut Manifest, name: String, hash: Option<String>) {
+    if let Some(hash) = hash {
+        manifest.record(name, ManifestEntry { hash });
+        save_manifest(manifest);
+    }
+}
+
+fn save_manifest(manifest: &mut Manifest) {
+    if let Err(e) = manifest.save() {
+        log::warn!("Could not save manifest: {}", e);
+    }
+}
+
+/// Names of the files whose patterns the watcher applies at the watched root
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".riceignore"];
+
+fn is_ignore_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| IGNORE_FILES.iter().any(|ignore_file| name == *ignore_file))
+}
+
+/// Gitignore-style rules for one watched root, rebuilt when its ignore files change
+struct IgnoreRules {
+    root: PathBuf,
+    /// Machine-wide ignore file, read before (so overridden by) the root's own
+    global: Option<PathBuf>,
+    matcher: Gitignore,
+}
+
+impl IgnoreRules {
+    fn load(root: &Path, global: Option<PathBuf>) -> Result<Self> {
+        Ok(Self {
+            root: root.to_path_buf(),
+            matcher: Self::build(root, global.as_deref())?,
+            global,
+        })
+    }
+
+    fn build(root: &Path, global: Option<&Path>) -> Result<Gitignore> {
+        let mut builder = GitignoreBuilder::new(root);
+        // Later patterns win, so local files can re-include what the global one ignores
+        if let Some(global) = global.filter(|p| p.is_file()) {
+            builder.add(global);
+        }
+        for ignore_file in IGNORE_FILES {
+            builder.add(root.join(ignore_file));
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Re-read the ignore files, keeping the current rules if they can't be parsed
+    fn reload(&mut self) -> Result<()> {
+        self.matcher = Self::build(&self.root, self.global.as_deref())?;
+        Ok(())
+    }
+
+    /// .git and gitignored paths are skipped for every kind of event
+    fn is_ignored(&self, event_path: &Path) -> bool {
+        if event_path.components().any(|c| c.as_os_str() == ".git") {
+            return true;
+        }
+
+        // Get relative path from the event path
+        let relative_path = event_path.strip_prefix(&self.root).unwrap_or(event_path);
+
+        // Normalize to forward slashes for gitignore matching
+        let rel_str = relative_path.to_string_lossy().replace("\\", "/");
+
+        matches!(
+            self.matcher.matched_path_or_any_parents(&rel_str, false),
+            ignore::Match::Ignore(_)
+        )
+    }
+}
+
+/// Ignore rules for every watched root
+struct WatchRoots(Vec<IgnoreRules>);
+
+impl WatchRoots {
+    /// The innermost watched root containing `path`
+    fn rules_for(&self, path: &Path) -> Option<&IgnoreRules> {
+        self.0
+            .iter()
+            .filter(|rules| path.starts_with(&rules.root))
+            .max_by_key(|rules| rules.root.components().count())
+    }
+
+    fn rules_for_mut(&mut self, path: &Path) -> Option<&mut IgnoreRules> {
+        self.0
+            .iter_mut()
+            .filter(|rules| path.starts_with(&rules.root))
+            .max_by_key(|rules| rules.root.components().count())
+    }
+
+    /// Apply the ignore rules of the root the path belongs to
+    fn is_ignored(&self, path: &Path) -> bool {
+        self.rules_for(path).is_some_and(|rules| rules.is_ignored(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_interval_tracks_debounce() {
+        assert_eq!(poll_interval(Duration::from_millis(200)), Duration::from_millis(50));
+        assert_eq!(poll_interval(Duration::from_millis(1)), Duration::from_millis(10));
+        assert_eq!(poll_interval(Duration::from_secs(30)), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_indexed_under_covers_renamed_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manifest = Manifest::load(&dir.path().join("manifest.json"));
+        for file in ["/repo/src/a.rs", "/repo/src/nested/b.rs", "/repo/src-old/c.rs"] {
+            manifest.record(file.to_string(), ManifestEntry::default());
+        }
+
+        assert_eq!(
+            indexed_under(&manifest, "/repo/src"),
+            ["/repo/src/a.rs", "/repo/src/nested/b.rs"]
+        );
+        assert_eq!(indexed_under(&manifest, "/repo/src-old/c.rs"), ["/repo/src-old/c.rs"]);
+        // Not in the manifest: the backend may still have it, so ask anyway
+        assert_eq!(indexed_under(&manifest, "/repo/other.rs"), ["/repo/other.rs"]);
+    }
+
+    #[test]
+    fn test_record_upload_skips_files_without_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let mut manifest = Manifest::load(&path);
+
+        record_upload(&mut manifest, "/repo/unreadable.rs".to_string(), None);
+        record_upload(&mut manifest, "/repo/a.rs".to_string(), Some("abc".to_string()));
+
+        let saved = Manifest::load(&path);
+        assert!(saved.is_unchanged("/repo/a.rs", "abc"));
+        assert!(!saved.entries().contains_key("/repo/unreadable.rs"));
+    }
+
+    #[test]
+    fn test_files_under_lists_nested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("moved/nested")).unwrap();
+        std::fs::write(dir.path().join("moved/a.rs"), "").unwrap();
+        std::fs::write(dir.path().join("moved/nested/b.rs"), "").unwrap();
+
+        let mut files = files_under(&dir.path().join("moved"));
+        files.sort();
+        assert_eq!(
+            files,
+            [dir.path().join("moved/a.rs"), dir.path().join("moved/nested/b.rs")]
+        );
+    }
+
+    #[test]
+    fn test_ignore_rules_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+
+        let mut rules = IgnoreRules::load(root, None).unwrap();
+        assert!(rules.is_ignored(&root.join("debug.log")));
+        assert!(!rules.is_ignored(&root.join("dist/app.js")));
+
+        std::fs::write(root.join(".riceignore"), "dist/\n").unwrap();
+        assert!(is_ignore_file(&root.join(".riceignore")));
+        rules.reload().unwrap();
+        assert!(rules.is_ignored(&root.join("dist/app.js")));
+    }
+
+    #[test]
+    fn test_global_ignore_is_overridden_by_local_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join("ignore");
+        std::fs::write(&global, "target/\n*.log\n").unwrap();
+        let root = dir.path().join("repo");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join(".riceignore"), "!keep.log\n").unwrap();
+
+        let rules = IgnoreRules::load(&root, Some(global)).unwrap();
+        assert!(rules.is_ignored(&root.join("target/out.rs")));
+        assert!(rules.is_ignored(&root.join("debug.log")));
+        assert!(!rules.is_ignored(&root.join("keep.log")));
+        assert!(!rules.is_ignored(&root.join("src/lib.rs")));
+    }
+
+    #[test]
+    fn test_each_root_uses_its_own_ignore_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        std::fs::create_dir_all(&a).unwrap();
+        std::fs::create_dir_all(&b).unwrap();
+        std::fs::write(a.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(b.join(".gitignore"), "target/\n").unwrap();
+
+        let roots = WatchRoots(vec![
+            IgnoreRules::load(&a, None).unwrap(),
+            IgnoreRules::load(&b, None).unwrap(),
+        ]);
+
+        assert!(roots.is_ignored(&a.join("debug.log")));
+        assert!(!roots.is_ignored(&b.join("debug.log")));
+        assert!(roots.is_ignored(&b.join("target/out.rs")));
+        assert!(!roots.is_ignored(&a.join("target/out.rs")));
+        assert_eq!(roots.rules_for(&b.join("src/lib.rs")).unwrap().root, b);
+    }
+}
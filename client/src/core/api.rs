@@ -1,48 +1,266 @@
+use super::config::{AppConfig, DEFAULT_RETRIES};
 use anyhow::{Context, Result};
-use reqwest::{multipart, Client};
+use reqwest::{multipart, Body, Client, RequestBuilder, Response, StatusCode};
+use serde::Serialize;
 use serde_json::Value;
+use std::fmt;
 use std::path::Path;
+use std::time::Duration;
+use tokio_util::io::ReaderStream;
 
+/// Delay before the first retry; doubled for each one after that
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// How a request to the backend failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// Nothing is listening at the backend URL
+    ConnectionRefused,
+    /// The host could not be reached at all (DNS, routing, TLS)
+    Unreachable,
+    /// Connected, but no answer within the configured timeout
+    Timeout,
+    /// The backend answered with a 4xx status
+    ClientError,
+    /// The backend answered with a 5xx status
+    ServerError,
+}
+
+/// A backend failure with enough detail to tell the user what to fix
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendError {
+    pub kind: FailureKind,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+/// The innermost error behind `err`, e.g. the OS error under a connect failure
+fn root_cause<'a>(
+    err: &'a (dyn std::error::Error + 'static),
+) -> &'a (dyn std::error::Error + 'static) {
+    let mut cause = err;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    cause
+}
+
+/// What removing a file from the index did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileDeletion {
+    /// The file's chunks were removed from the index
+    Deleted { chunks: u64 },
+    /// Nothing was indexed under the path
+    NotIndexed,
+}
+
+/// Cheap to clone: clones share the underlying connection pool
+#[derive(Clone)]
 pub struct ApiClient {
     client: Client,
     base_url: String,
+    retries: u32,
+    timeout: Duration,
 }
 
 impl ApiClient {
-    pub fn new(base_url: &str) -> Self {
+    pub fn from_config(config: &AppConfig) -> Self {
         Self {
-            client: Client::new(),
+            retries: config.retries,
+            ..Self::with_timeouts(
+                &config.backend_url,
+                Duration::from_secs(config.connect_timeout_secs),
+                Duration::from_secs(config.timeout_secs),
+            )
+        }
+    }
+
+    fn with_timeouts(base_url: &str, connect_timeout: Duration, timeout: Duration) -> Self {
+        let client = Client::builder()
+            .connect_timeout(connect_timeout)
+            .timeout(timeout)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            client,
             base_url: base_url.trim_end_matches('/').to_string(),
+            retries: DEFAULT_RETRIES,
+            timeout,
+        }
+    }
+
+    /// Turn a transport error into a message that says what kind of failure it was
+    fn describe_error(&self, err: reqwest::Error) -> anyhow::Error {
+        if err.is_timeout() || err.is_connect() {
+            self.transport_error(&err).into()
+        } else {
+            err.into()
+        }
+    }
+
+    /// Classify a failure to get any response from the backend
+    fn transport_error(&self, err: &reqwest::Error) -> BackendError {
+        let cause = root_cause(err);
+        let refused = cause
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|io| io.kind() == std::io::ErrorKind::ConnectionRefused);
+        let (kind, message) = if err.is_timeout() {
+            (
+                FailureKind::Timeout,
+                format!(
+                    "Timed out after {}s waiting for {} (backend is slow or overloaded)",
+                    self.timeout.as_secs_f64(),
+                    self.base_url
+                ),
+            )
+        } else if refused {
+            (
+                FailureKind::ConnectionRefused,
+                format!("Connection refused by {} (is the backend running?)", self.base_url),
+            )
+        } else {
+            (
+                FailureKind::Unreachable,
+                format!("Could not connect to {}: {}", self.base_url, cause),
+            )
+        };
+        BackendError { kind, url: self.base_url.clone(), status: None, message }
+    }
+
+    /// Describe a response with an unsuccessful status
+    fn status_error(&self, action: &str, status: StatusCode) -> BackendError {
+        let kind = if status.is_server_error() {
+            FailureKind::ServerError
+        } else {
+            FailureKind::ClientError
+        };
+        BackendError {
+            kind,
+            url: self.base_url.clone(),
+            status: Some(status.as_u16()),
+            message: format!("{} failed: {} answered {}", action, self.base_url, status),
+        }
+    }
+
+    /// Send a request, retrying connection failures and 5xx responses with backoff
+    ///
+    /// `build` is called once per attempt since request bodies can't be reused.
+    /// 4xx responses are returned immediately; they won't succeed on a retry.
+    async fn send_with_retry<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn() -> Result<RequestBuilder>,
+    {
+        let mut delay = RETRY_BASE_DELAY;
+        let mut attempt = 0;
+        loop {
+            let result = build()?.send().await;
+            let reason = match &result {
+                Ok(resp) if resp.status().is_server_error() => resp.status().to_string(),
+                Err(e) if e.is_connect() || e.is_timeout() => e.to_string(),
+                _ => return result.map_err(|e| self.describe_error(e)),
+            };
+            if attempt >= self.retries {
+                return result.map_err(|e| self.describe_error(e));
+            }
+
+            attempt += 1;
+            log::warn!(
+                "Request failed ({}), retrying in {}ms (retry {}/{})",
+                reason,
+                delay.as_millis(),
+                attempt,
+                self.retries
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
         }
     }
 
     pub async fn health_check(&self) -> bool {
-        match self
+        self.ping().await.is_ok()
+    }
+
+    /// Probe the backend's `/health` once, without retries, so a down backend
+    /// is reported quickly
+    ///
+    /// Only reachability counts: a backend reporting itself degraded still
+    /// answers 200 and can serve searches.
+    pub async fn ping(&self) -> std::result::Result<(), BackendError> {
+        let resp = self
             .client
-            .get(format!("{}/healthz", self.base_url))
+            .get(format!("{}/health", self.base_url))
             .send()
             .await
-        {
-            Ok(resp) => resp.status().is_success(),
-            Err(_) => false,
+            .map_err(|e| self.transport_error(&e))?;
+        if !resp.status().is_success() {
+            return Err(self.status_error("Health check", resp.status()));
         }
+        Ok(())
     }
 
-    pub async fn index_file(&self, path: &Path, upload_path: &str, org_id: &str) -> Result<Value> {
-        // Read file content eagerly for simplicity/robustness (<10MB files usually)
-        let content = tokio::fs::read(path).await.context("Failed to read file")?;
-        
-        // Use provided upload_path (relative) as filename
-        let part = multipart::Part::bytes(content).file_name(upload_path.to_string());
-        let form = multipart::Form::new()
-            .part("file", part)
-            .text("org_id", org_id.to_string());
+    /// Index statistics from the backend: chunk counts overall and per store,
+    /// plus the BM25 index's own health report under `bm25`
+    pub async fn stats(&self) -> Result<Value> {
+        let url = format!("{}/api/v1/index/stats", self.base_url);
+        let resp = self.send_with_retry(|| Ok(self.client.get(&url))).await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Stats request failed: {}", resp.status());
+        }
+
+        let json: Value = resp.json().await?;
+        Ok(json)
+    }
 
+    /// Fail unless the BM25 index analyzes text with `tokenizer`
+    ///
+    /// The analyzer is fixed when the index is created, so a mismatch means
+    /// queries and indexed chunks would be tokenized differently than expected.
+    pub async fn require_tokenizer(&self, tokenizer: &str) -> Result<()> {
+        let stats = self.stats().await?;
+        match stats["bm25"]["tokenizer"].as_str() {
+            Some(active) if active == tokenizer => Ok(()),
+            Some(active) => anyhow::bail!(
+                "The BM25 index uses the `{}` tokenizer, not `{}`; recreate it with \
+                 TANTIVY_TOKENIZER={} and reindex",
+                active,
+                tokenizer,
+                tokenizer
+            ),
+            None => anyhow::bail!("The BM25 service did not report its tokenizer"),
+        }
+    }
+
+    pub async fn index_file(&self, path: &Path, upload_path: &str, org_id: &str) -> Result<Value> {
+        let url = format!("{}/api/v1/ingest/file", self.base_url);
         let resp = self
-            .client
-            .post(format!("{}/api/v1/ingest/file", self.base_url))
-            .multipart(form)
-            .send()
+            .send_with_retry(|| {
+                // Stream the file so memory use doesn't grow with its size; it is
+                // reopened per attempt because a consumed stream can't be resent
+                let file = std::fs::File::open(path).context("Failed to read file")?;
+                let length = file.metadata().context("Failed to read file")?.len();
+                let body = Body::wrap_stream(ReaderStream::new(tokio::fs::File::from_std(file)));
+
+                // Use provided upload_path (relative) as filename
+                let part = multipart::Part::stream_with_length(body, length)
+                    .file_name(upload_path.to_string());
+                let form = multipart::Form::new()
+                    .part("file", part)
+                    .text("org_id", org_id.to_string());
+                Ok(self.client.post(&url).multipart(form))
+            })
             .await?;
 
         if !resp.status().is_success() {
@@ -53,26 +271,504 @@ impl ApiClient {
         Ok(json)
     }
 
-    pub async fn search(&self, query: &str, limit: usize, hybrid: bool) -> Result<Value> {
+    /// Remove every chunk indexed for `path` (an upload name) within `org_id`.
+    ///
+    /// The backend finds chunks by the path they were uploaded under, so this
+    /// works after the file is gone. A path with nothing indexed is
+    /// [`FileDeletion::NotIndexed`] rather than an error.
+    pub async fn delete_file(&self, path: &str, org_id: &str) -> Result<FileDeletion> {
+        let url = format!("{}/api/v1/index/file", self.base_url);
+        let resp = self
+            .send_with_retry(|| {
+                Ok(self.client.delete(&url).query(&[("path", path), ("org_id", org_id)]))
+            })
+            .await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Delete failed: {}", resp.status());
+        }
+
+        let json: Value = resp.json().await?;
+        match json["status"].as_str() {
+            Some("deleted") => Ok(FileDeletion::Deleted {
+                chunks: json["chunks_removed"].as_u64().unwrap_or(0),
+            }),
+            Some("not_found") => Ok(FileDeletion::NotIndexed),
+            _ => anyhow::bail!("Unexpected delete response: {}", json),
+        }
+    }
+
+    /// Delete everything indexed for `org_id` from the vector and BM25 indexes,
+    /// returning how many chunks were removed
+    pub async fn clear(&self, org_id: &str) -> Result<u64> {
+        let url = format!("{}/api/v1/index/clear", self.base_url);
+        let resp = self
+            .send_with_retry(|| Ok(self.client.post(&url).query(&[("org_id", org_id)])))
+            .await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Clear failed: {}", resp.status());
+        }
+
+        let json: Value = resp.json().await?;
+        if json["status"] != "cleared" {
+            anyhow::bail!("Unexpected clear response: {}", json);
+        }
+        Ok(json["chunks_removed"].as_u64().unwrap_or(0))
+    }
+
+    /// Every file path with indexed chunks in `org_id`, as upload names
+    pub async fn list_paths(&self, org_id: &str) -> Result<Vec<String>> {
+        let url = format!("{}/api/v1/files/list", self.base_url);
+        let resp = self
+            .send_with_retry(|| Ok(self.client.get(&url).query(&[("org_id", org_id)])))
+            .await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Path listing failed: {}", resp.status());
+        }
+
+        let json: Value = resp.json().await?;
+        let paths = json
+            .get("files")
+            .and_then(|files| files.as_array())
+            .context("Path listing has no files array")?;
+        Ok(paths
+            .iter()
+            .filter_map(|path| path.as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Chunks indexed for `path` (an upload name) within `org_id`, in line order
+    pub async fn file_chunks(&self, path: &str, org_id: &str) -> Result<Value> {
+        let url = format!("{}/api/v1/index/chunks", self.base_url);
+        let resp = self
+            .send_with_retry(|| {
+                Ok(self.client.get(&url).query(&[("path", path), ("org_id", org_id)]))
+            })
+            .await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Chunk lookup failed: {}", resp.status());
+        }
+
+        let json: Value = resp.json().await?;
+        Ok(json)
+    }
+
+    pub async fn search(
+        &self,
+        query: &str,
+        limit: usize,
+        hybrid: bool,
+        org_id: &str,
+    ) -> Result<Value> {
         let body = serde_json::json!({
             "query": query,
             "mode": "search",
             "hybrid": hybrid,
-            "limit": limit
+            "limit": limit,
+            "org_id": org_id
         });
 
+        let url = format!("{}/api/v1/search/query", self.base_url);
         let resp = self
-            .client
-            .post(format!("{}/api/v1/search/query", self.base_url))
-            .json(&body)
-            .send()
+            .send_with_retry(|| Ok(self.client.post(&url).json(&body)))
             .await?;
 
         if !resp.status().is_success() {
-            anyhow::bail!("Search failed: {}", resp.status());
+            return Err(self.status_error("Search", resp.status()).into());
         }
 
         let json: Value = resp.json().await?;
         Ok(json)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// A client for `url` with the default config
+    fn client(url: &str) -> ApiClient {
+        ApiClient::from_config(&AppConfig {
+            backend_url: url.to_string(),
+            ..AppConfig::default()
+        })
+    }
+
+    /// Raw requests (headers and body) received by a stub server, in order
+    type Requests = Arc<Mutex<Vec<Vec<u8>>>>;
+
+    /// Read one HTTP/1.1 request, honouring Content-Length or chunked encoding
+    async fn read_request(socket: &mut TcpStream) -> Vec<u8> {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 8192];
+        let mut header_end = None;
+        loop {
+            if header_end.is_none() {
+                header_end = request.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4);
+            }
+            if let Some(end) = header_end {
+                let headers = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                let length = headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|value| value.trim().parse::<usize>().ok());
+                let done = match length {
+                    Some(length) => request.len() >= end + length,
+                    None if headers.contains("transfer-encoding: chunked") => {
+                        request.ends_with(b"0\r\n\r\n")
+                    }
+                    None => true,
+                };
+                if done {
+                    return request;
+                }
+            }
+
+            let read = socket.read(&mut buf).await.unwrap();
+            if read == 0 {
+                return request;
+            }
+            request.extend_from_slice(&buf[..read]);
+        }
+    }
+
+    /// Serve one canned HTTP status per connection (the last one repeats),
+    /// returning the base URL and the requests received
+    async fn stub_server(statuses: Vec<u16>) -> (String, Requests) {
+        stub_responses(statuses.into_iter().map(|status| (status, "{}")).collect()).await
+    }
+
+    /// Like [`stub_server`], with a JSON body for each response
+    async fn stub_responses(responses: Vec<(u16, &'static str)>) -> (String, Requests) {
+        stub_with(move |n, _| responses[n.min(responses.len() - 1)]).await
+    }
+
+    /// Serve each `"METHOD /path"` route with its status and JSON body, and
+    /// 404 for anything else, like a backend that only has those routes
+    async fn stub_routes(routes: Vec<(&'static str, u16, &'static str)>) -> (String, Requests) {
+        stub_with(move |_, request| {
+            let target = request.lines().next().unwrap_or_default();
+            routes
+                .iter()
+                .find(|(route, _, _)| {
+                    target
+                        .strip_prefix(route)
+                        .is_some_and(|rest| rest.starts_with([' ', '?']))
+                })
+                .map_or((404, r#"{"detail": "Not Found"}"#), |&(_, status, body)| (status, body))
+        })
+        .await
+    }
+
+    /// Answer the `n`th request (counting from 0) with whatever `respond` returns
+    async fn stub_with<F>(respond: F) -> (String, Requests)
+    where
+        F: Fn(usize, &str) -> (u16, &'static str) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Requests::default();
+        let received = requests.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut socket).await;
+                let (status, body) = respond(
+                    received.lock().unwrap().len(),
+                    &String::from_utf8_lossy(&request),
+                );
+                received.lock().unwrap().push(request);
+
+                let response = format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors_then_succeeds() {
+        let (url, requests) = stub_server(vec![503, 502, 200]).await;
+        let client = client(&url);
+
+        assert!(client.search("query", 5, false, "public").await.is_ok());
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_client_errors() {
+        let (url, requests) = stub_server(vec![404]).await;
+        let client = client(&url);
+
+        assert!(client.search("query", 5, false, "public").await.is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_configured_retries() {
+        let (url, requests) = stub_server(vec![500]).await;
+        let client = ApiClient {
+            retries: 1,
+            ..client(&url)
+        };
+
+        assert!(client.search("query", 5, false, "public").await.is_err());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_index_file_sends_upload_name_and_org() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        std::fs::write(&file, "pub fn answer() -> u32 { 42 }").unwrap();
+
+        let (url, requests) = stub_server(vec![200]).await;
+        let client = client(&url);
+        client
+            .index_file(&file, "/repo/src/lib.rs", "acme")
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let request = String::from_utf8_lossy(&requests[0]);
+        assert!(request.starts_with("POST /api/v1/ingest/file "));
+        assert!(request.contains("filename=\"/repo/src/lib.rs\""));
+        assert!(request.contains("name=\"org_id\"\r\n\r\nacme"));
+        assert!(request.contains("pub fn answer() -> u32 { 42 }"));
+    }
+
+    #[tokio::test]
+    async fn test_file_chunks_queries_backend_by_path_and_org() {
+        let (url, requests) = stub_server(vec![200]).await;
+        client(&url).file_chunks("/repo/src/lib.rs", "acme").await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let request = String::from_utf8_lossy(&requests[0]);
+        let request_line = request.lines().next().unwrap();
+        assert!(request_line.starts_with("GET /api/v1/index/chunks?"), "{}", request_line);
+        assert!(request_line.contains("path=%2Frepo%2Fsrc%2Flib.rs"), "{}", request_line);
+        assert!(request_line.contains("org_id=acme"), "{}", request_line);
+    }
+
+    #[tokio::test]
+    async fn test_delete_file_uses_backend_delete_route() {
+        let (url, requests) = stub_responses(vec![
+            (200, r#"{"status": "deleted", "path": "/repo/src/lib.rs", "chunks_removed": 3}"#),
+            (200, r#"{"status": "not_found", "path": "/repo/src/lib.rs", "chunks_removed": 0}"#),
+            (404, r#"{"detail": "Not Found"}"#),
+            (200, "{}"),
+        ])
+        .await;
+        let client = client(&url);
+        assert_eq!(
+            client.delete_file("/repo/src/lib.rs", "acme").await.unwrap(),
+            FileDeletion::Deleted { chunks: 3 }
+        );
+
+        {
+            let requests = requests.lock().unwrap();
+            let request = String::from_utf8_lossy(&requests[0]);
+            let request_line = request.lines().next().unwrap();
+            assert!(request_line.starts_with("DELETE /api/v1/index/file?"), "{}", request_line);
+            assert!(request_line.contains("path=%2Frepo%2Fsrc%2Flib.rs"), "{}", request_line);
+            assert!(request_line.contains("org_id=acme"), "{}", request_line);
+        }
+
+        assert_eq!(
+            client.delete_file("/repo/src/lib.rs", "acme").await.unwrap(),
+            FileDeletion::NotIndexed
+        );
+        // A missing route, or an answer that doesn't say what happened, is a
+        // failure rather than a deleted file
+        assert!(client.delete_file("/repo/src/lib.rs", "acme").await.is_err());
+        assert!(client.delete_file("/repo/src/lib.rs", "acme").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stats_reads_backend_index_stats() {
+        let (url, requests) = stub_responses(vec![(
+            200,
+            r#"{"status": "ok", "indexed_chunks": 12, "stores": {"public": 12}, "bm25": {}}"#,
+        )])
+        .await;
+        let stats = client(&url).stats().await.unwrap();
+        assert_eq!(stats["indexed_chunks"], 12);
+
+        let requests = requests.lock().unwrap();
+        let request = String::from_utf8_lossy(&requests[0]);
+        assert!(request.starts_with("GET /api/v1/index/stats "), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn test_require_tokenizer_matches_bm25_report() {
+        let (url, _) = stub_responses(vec![(
+            200,
+            r#"{"status": "ok", "indexed_chunks": 0, "stores": {}, "bm25": {"tokenizer": "code"}}"#,
+        )])
+        .await;
+        client(&url).require_tokenizer("code").await.unwrap();
+
+        let err = client(&url).require_tokenizer("default").await.unwrap_err().to_string();
+        assert!(err.contains("uses the `code` tokenizer, not `default`"), "{}", err);
+
+        // A BM25 service that can't say which analyzer it uses isn't trusted
+        let (url, _) = stub_responses(vec![(200, r#"{"bm25": {"status": "unavailable"}}"#)]).await;
+        assert!(client(&url).require_tokenizer("code").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_clear_only_succeeds_when_backend_cleared() {
+        let (url, requests) = stub_responses(vec![
+            (200, r#"{"status": "cleared", "org_id": "acme", "chunks_removed": 7}"#),
+            (503, r#"{"detail": "Index unavailable"}"#),
+            (200, "{}"),
+        ])
+        .await;
+        let client = ApiClient {
+            retries: 0,
+            ..client(&url)
+        };
+        assert_eq!(client.clear("acme").await.unwrap(), 7);
+
+        {
+            let requests = requests.lock().unwrap();
+            let request = String::from_utf8_lossy(&requests[0]);
+            assert!(request.starts_with("POST /api/v1/index/clear?org_id=acme "), "{}", request);
+        }
+
+        assert!(client.clear("acme").await.is_err());
+        assert!(client.clear("acme").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_paths_reads_backend_file_list() {
+        let (url, requests) = stub_responses(vec![
+            (200, r#"{"files": ["/repo/a.rs", "/repo/b.rs"], "count": 2}"#),
+            (404, r#"{"detail": "Not Found"}"#),
+        ])
+        .await;
+        let client = client(&url);
+        assert_eq!(client.list_paths("acme").await.unwrap(), ["/repo/a.rs", "/repo/b.rs"]);
+
+        {
+            let requests = requests.lock().unwrap();
+            let request = String::from_utf8_lossy(&requests[0]);
+            assert!(request.starts_with("GET /api/v1/files/list?org_id=acme "), "{}", request);
+        }
+
+        assert!(client.list_paths("acme").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_is_scoped_to_org() {
+        let (url, requests) = stub_server(vec![200]).await;
+        let client = client(&url);
+        client.search("tokio", 5, true, "acme").await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let request = String::from_utf8_lossy(&requests[0]);
+        let body = request.split("\r\n\r\n").nth(1).unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["org_id"], "acme");
+        assert_eq!(body["query"], "tokio");
+    }
+
+    #[tokio::test]
+    async fn test_index_file_streams_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("large.txt");
+        let content: Vec<u8> = (0..5 * 1024 * 1024).map(|i| b'a' + (i % 26) as u8).collect();
+        std::fs::write(&file, &content).unwrap();
+
+        let (url, requests) = stub_server(vec![200]).await;
+        client(&url)
+            .index_file(&file, "/repo/large.txt", "public")
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let request = &requests[0];
+        let start = request
+            .windows(64)
+            .position(|w| w == &content[..64])
+            .unwrap();
+        assert_eq!(&request[start..start + content.len()], &content[..]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_backend_reports_timeout() {
+        // Accept connections but never answer
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            loop {
+                held.push(listener.accept().await.unwrap());
+            }
+        });
+
+        let client = ApiClient {
+            retries: 0,
+            ..ApiClient::with_timeouts(&url, Duration::from_secs(5), Duration::from_millis(200))
+        };
+        let err = client.search("query", 5, false, "public").await.unwrap_err();
+        assert!(err.to_string().starts_with("Timed out"), "{}", err);
+        let err = err.downcast_ref::<BackendError>().unwrap();
+        assert_eq!(err.kind, FailureKind::Timeout);
+    }
+
+    #[tokio::test]
+    async fn test_ping_tells_refused_from_error_statuses() {
+        // A port that was just free has nothing listening on it
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let err = client(&url).ping().await.unwrap_err();
+        assert_eq!(err.kind, FailureKind::ConnectionRefused);
+        assert_eq!(err.status, None);
+
+        let (url, _) = stub_server(vec![503]).await;
+        let err = client(&url).ping().await.unwrap_err();
+        assert_eq!(err.kind, FailureKind::ServerError);
+        assert_eq!(err.status, Some(503));
+
+        let (url, _) = stub_server(vec![200, 404]).await;
+        let client = client(&url);
+        assert!(client.ping().await.is_ok());
+        let err = client.search("query", 5, false, "public").await.unwrap_err();
+        let err = err.downcast_ref::<BackendError>().unwrap();
+        assert_eq!(err.kind, FailureKind::ClientError);
+        assert_eq!(err.status, Some(404));
+    }
+
+    #[tokio::test]
+    async fn test_ping_uses_backend_health_route() {
+        let (url, requests) = stub_routes(vec![(
+            "GET /health",
+            200,
+            r#"{"status": "degraded", "components": {}}"#,
+        )])
+        .await;
+        assert!(client(&url).ping().await.is_ok());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // A server without that route isn't the backend
+        let (url, _) = stub_routes(vec![("GET /healthz", 200, "{}")]).await;
+        let err = client(&url).ping().await.unwrap_err();
+        assert_eq!(err.status, Some(404));
+    }
+}
@@ -1,26 +1,161 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use config::{Config, File};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Files larger than this are skipped when indexing unless allowlisted
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Times a request is retried after a connection error or 5xx response
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Seconds to wait for a connection to the backend
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// Seconds to wait for a whole request, including the response body
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Org searched and indexed when a command is given no `--org-id`
+pub const DEFAULT_STORE: &str = "public";
+
+/// Keys accepted by `ricesearch config set`
+const CONFIG_KEYS: &[&str] = &[
+    "backend_url",
+    "user_id",
+    "store",
+    "max_file_bytes",
+    "allow_extensions",
+    "retries",
+    "connect_timeout_secs",
+    "timeout_secs",
+];
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct AppConfig {
     pub backend_url: String,
     pub user_id: String,
+    /// Org used by commands run without `--org-id`; `RICE_STORE` overrides it
+    pub store: String,
+    /// Skip files larger than this many bytes
+    pub max_file_bytes: u64,
+    /// File name suffixes (e.g. `.min.js`) exempt from the size and binary checks
+    pub allow_extensions: Vec<String>,
+    /// Retries for requests that fail with a connection error or 5xx response
+    pub retries: u32,
+    /// Seconds to wait for a connection to the backend
+    pub connect_timeout_secs: u64,
+    /// Seconds to wait for a whole request
+    pub timeout_secs: u64,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             backend_url: "http://localhost:8000".to_string(),
-            user_id: "default-user".to_string(), // TODO: Generate UUID
+            // Only used when no config file can be written; see ensure_config_file
+            user_id: "default-user".to_string(),
+            store: DEFAULT_STORE.to_string(),
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            allow_extensions: Vec::new(),
+            retries: DEFAULT_RETRIES,
+            connect_timeout_secs: DEFAULT_CONNECT_TIMEOUT_SECS,
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
         }
     }
 }
 
-pub fn load_config() -> Result<AppConfig> {
+impl AppConfig {
+    /// The org a command targets: its `--org-id` if given, else the configured store
+    pub fn org_id(&self, org_id: Option<&str>) -> String {
+        org_id.unwrap_or(&self.store).to_string()
+    }
+
+    /// Set a configuration value by key, validating it first
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "backend_url" => {
+                reqwest::Url::parse(value)
+                    .with_context(|| format!("Invalid backend_url: {}", value))?;
+                self.backend_url = value.to_string();
+            }
+            "user_id" => {
+                if value.trim().is_empty() {
+                    anyhow::bail!("user_id must not be empty");
+                }
+                self.user_id = value.to_string();
+            }
+            "store" => {
+                if value.trim().is_empty() {
+                    anyhow::bail!("store must not be empty");
+                }
+                self.store = value.to_string();
+            }
+            "max_file_bytes" => {
+                self.max_file_bytes = value
+                    .parse()
+                    .with_context(|| format!("Invalid max_file_bytes: {}", value))?;
+            }
+            "allow_extensions" => {
+                // Comma-separated, e.g. ".min.js,.lock"
+                self.allow_extensions = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|ext| !ext.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            "retries" => {
+                self.retries = value
+                    .parse()
+                    .with_context(|| format!("Invalid retries: {}", value))?;
+            }
+            "connect_timeout_secs" | "timeout_secs" => {
+                let secs: u64 = value
+                    .parse()
+                    .with_context(|| format!("Invalid {}: {}", key, value))?;
+                anyhow::ensure!(secs > 0, "{} must be greater than zero", key);
+                if key == "connect_timeout_secs" {
+                    self.connect_timeout_secs = secs;
+                } else {
+                    self.timeout_secs = secs;
+                }
+            }
+            _ => anyhow::bail!(
+                "Unknown config key '{}' (expected one of: {})",
+                key,
+                CONFIG_KEYS.join(", ")
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Directory holding ricesearch's local state, `~/.config/ricesearch` on Linux
+pub fn config_dir() -> PathBuf {
     let config_dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
-    let config_path = config_dir.join("ricesearch").join("config.toml");
+    config_dir.join("ricesearch")
+}
+
+/// Location of the config file, `~/.config/ricesearch/config.toml` on Linux
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// Machine-wide gitignore-style patterns, `~/.config/ricesearch/ignore` on Linux
+///
+/// They apply to every scanned or watched tree, below the tree's own ignore files.
+pub fn global_ignore_path() -> PathBuf {
+    config_dir().join("ignore")
+}
+
+pub fn load_config() -> Result<AppConfig> {
+    let config_path = config_path();
+
+    // First run: persist a generated user_id so it stays stable across runs
+    if let Err(e) = ensure_config_file(&config_path) {
+        log::warn!("Could not create {}: {}", config_path.display(), e);
+    }
 
     let s = Config::builder()
         .add_source(File::from(config_path).required(false))
@@ -34,3 +169,118 @@ pub fn load_config() -> Result<AppConfig> {
         Err(_) => Ok(AppConfig::default()),
     }
 }
+
+/// Create the config file with a fresh v4 UUID `user_id` if it doesn't exist yet
+///
+/// Existing files are left untouched.
+pub fn ensure_config_file(path: &Path) -> Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+    let config = AppConfig {
+        user_id: uuid::Uuid::new_v4().to_string(),
+        ..AppConfig::default()
+    };
+    save_config(&config, path)
+}
+
+/// Load only what is persisted in the config file, ignoring environment overrides
+pub fn load_config_file(path: &Path) -> Result<AppConfig> {
+    if !path.exists() {
+        return Ok(AppConfig::default());
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Write the config file, creating its directory if needed
+pub fn save_config(config: &AppConfig, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let content = toml::to_string_pretty(config)?;
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_set_validates_keys_and_values() {
+        let mut config = AppConfig::default();
+
+        config.set("backend_url", "http://search.internal:8000").unwrap();
+        assert_eq!(config.backend_url, "http://search.internal:8000");
+
+        assert!(config.set("backend_url", "not a url").is_err());
+        assert!(config.set("colour", "blue").is_err());
+        assert_eq!(config.backend_url, "http://search.internal:8000");
+
+        config.set("max_file_bytes", "1048576").unwrap();
+        assert_eq!(config.max_file_bytes, 1_048_576);
+        assert!(config.set("max_file_bytes", "2MB").is_err());
+
+        config.set("allow_extensions", ".min.js, .lock").unwrap();
+        assert_eq!(config.allow_extensions, vec![".min.js", ".lock"]);
+
+        config.set("timeout_secs", "60").unwrap();
+        assert_eq!(config.timeout_secs, 60);
+        assert!(config.set("connect_timeout_secs", "0").is_err());
+    }
+
+    #[test]
+    fn test_org_id_falls_back_to_configured_store() {
+        let mut config = AppConfig::default();
+        assert_eq!(config.org_id(None), DEFAULT_STORE);
+
+        config.set("store", "acme").unwrap();
+        assert_eq!(config.org_id(None), "acme");
+        assert_eq!(config.org_id(Some("globex")), "globex");
+        assert!(config.set("store", " ").is_err());
+    }
+
+    #[test]
+    fn test_first_run_generates_stable_user_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ricesearch").join("config.toml");
+
+        ensure_config_file(&path).unwrap();
+        let first = load_config_file(&path).unwrap();
+        assert!(uuid::Uuid::parse_str(&first.user_id).is_ok());
+
+        // A second run keeps the existing id
+        ensure_config_file(&path).unwrap();
+        assert_eq!(load_config_file(&path).unwrap().user_id, first.user_id);
+    }
+
+    #[test]
+    fn test_existing_user_id_is_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        std::fs::write(&path, "backend_url = \"http://localhost:8000\"\nuser_id = \"alice\"\n").unwrap();
+
+        ensure_config_file(&path).unwrap();
+        assert_eq!(load_config_file(&path).unwrap().user_id, "alice");
+    }
+
+    #[test]
+    fn test_save_and_reload_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ricesearch").join("config.toml");
+
+        // Missing file falls back to defaults
+        let mut config = load_config_file(&path).unwrap();
+        assert_eq!(config.backend_url, AppConfig::default().backend_url);
+
+        config.set("user_id", "alice").unwrap();
+        save_config(&config, &path).unwrap();
+
+        let reloaded = load_config_file(&path).unwrap();
+        assert_eq!(reloaded.user_id, "alice");
+        assert_eq!(reloaded.backend_url, config.backend_url);
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::config::config_dir;
+
+/// Location of the manifest, `~/.config/ricesearch/manifest.json` on Linux
+pub fn manifest_path() -> PathBuf {
+    config_dir().join("manifest.json")
+}
+
+/// A manifest shared between the tasks of one scan or watch
+pub type SharedManifest = Arc<Mutex<Manifest>>;
+
+/// How long a save waits for another process to finish saving
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A lock older than this was left behind by a saver that died
+const STALE_LOCK_AGE: Duration = Duration::from_secs(5);
+
+/// What was uploaded for a file at its last successful upload
+///
+/// Chunk ids aren't kept: ingestion is queued, so the upload response has
+/// none, and the backend deletes a file's chunks by its path. Manifests
+/// written with a `chunk_ids` list still load; the list is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Content hash of the uploaded file
+    pub hash: String,
+}
+
+/// Per-file record of uploads, keyed by upload name (normalized absolute path).
+///
+/// Lets a scan skip files whose content hasn't changed since they were indexed.
+pub struct Manifest {
+    path: PathBuf,
+    entries: BTreeMap<String, ManifestEntry>,
+    /// Records (`Some`) and removals (`None`) since the last load or save,
+    /// replayed onto the file on disk when saving
+    changes: BTreeMap<String, Option<ManifestEntry>>,
+    /// Whether the file's current entries are dropped on save, not merged
+    replace: bool,
+    dirty: bool,
+}
+
+impl Manifest {
+    /// Load the manifest from `path`, starting empty if it is missing
+    ///
+    /// A corrupt manifest is discarded and rebuilt from scratch as files are
+    /// uploaded again; it is overwritten on the next save.
+    pub fn load(path: &Path) -> Self {
+        let (entries, dirty) = match read_entries(path) {
+            Ok(entries) => (entries, false),
+            Err(e) => {
+                log::warn!("Rebuilding corrupt manifest {}: {}", path.display(), e);
+                (BTreeMap::new(), true)
+            }
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            entries,
+            changes: BTreeMap::new(),
+            replace: dirty,
+            dirty,
+        }
+    }
+
+    pub fn shared(self) -> SharedManifest {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn entries(&self) -> &BTreeMap<String, ManifestEntry> {
+        &self.entries
+    }
+
+    /// Whether `hash` matches the hash recorded for `file` at its last upload
+    pub fn is_unchanged(&self, file: &str, hash: &str) -> bool {
+        self.entries.get(file).is_some_and(|entry| entry.hash == hash)
+    }
+
+    /// Record a file that was just uploaded
+    pub fn record(&mut self, file: String, entry: ManifestEntry) {
+        if self.entries.get(&file) != Some(&entry) {
+            self.entries.insert(file.clone(), entry.clone());
+            self.changes.insert(file, Some(entry));
+            self.dirty = true;
+        }
+    }
+
+    /// Forget a file that is no longer indexed, returning what it had produced
+    pub fn remove(&mut self, file: &str) -> Option<ManifestEntry> {
+        let entry = self.entries.remove(file);
+        if entry.is_some() {
+            self.changes.insert(file.to_string(), None);
+            self.dirty = true;
+        }
+        entry
+    }
+
+    /// Forget every file so the next scan uploads all of them again
+    pub fn clear(&mut self) {
+        if !self.entries.is_empty() {
+            self.entries.clear();
+            self.changes.clear();
+            self.replace = true;
+            self.dirty = true;
+        }
+    }
+
+    /// Write the manifest back to disk if anything changed
+    ///
+    /// Another process (a watcher next to a one-off index) may have saved
+    /// since this manifest was loaded, so under a lock file the manifest is
+    /// re-read and this one's changes are applied on top of it. The file is
+    /// then replaced atomically, so readers never see a partial manifest.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let _lock = SaveLock::acquire(&self.path)?;
+
+        // A file that turned corrupt since loading has nothing worth keeping
+        let mut entries = if self.replace {
+            BTreeMap::new()
+        } else {
+            read_entries(&self.path).unwrap_or_default()
+        };
+        for (file, change) in &self.changes {
+            match change {
+                Some(entry) => entries.insert(file.clone(), entry.clone()),
+                None => entries.remove(file),
+            };
+        }
+
+        let contents = serde_json::to_string(&entries)?;
+        let temp_path = with_suffix(&self.path, &format!(".{}.tmp", std::process::id()));
+        std::fs::write(&temp_path, contents)
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        std::fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+
+        self.entries = entries;
+        self.changes.clear();
+        self.replace = false;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// Entries of the manifest at `path`; a missing file has none
+fn read_entries(path: &Path) -> Result<BTreeMap<String, ManifestEntry>> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Exclusive right to save a manifest, released on drop
+///
+/// A `<manifest>.lock` file created with `create_new`, which works the same
+/// on every platform and needs no locking crate.
+struct SaveLock(PathBuf);
+
+impl SaveLock {
+    fn acquire(manifest: &Path) -> Result<Self> {
+        let path = with_suffix(manifest, ".lock");
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self(path)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let age = std::fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+                    if age.is_some_and(|age| age > STALE_LOCK_AGE) {
+                        log::warn!("Removing stale manifest lock {}", path.display());
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        anyhow::bail!("Timed out waiting for {}", path.display());
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for SaveLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(hash: &str) -> ManifestEntry {
+        ManifestEntry { hash: hash.to_string() }
+    }
+
+    #[test]
+    fn test_round_trip_and_change_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("manifest.json");
+
+        let mut manifest = Manifest::load(&path);
+        assert!(!manifest.is_unchanged("/repo/a.rs", "abc"));
+        manifest.record("/repo/a.rs".to_string(), entry("abc"));
+        manifest.save().unwrap();
+
+        let mut manifest = Manifest::load(&path);
+        assert!(manifest.is_unchanged("/repo/a.rs", "abc"));
+        assert!(!manifest.is_unchanged("/repo/a.rs", "def"));
+        assert_eq!(manifest.remove("/repo/a.rs").unwrap(), entry("abc"));
+        assert!(manifest.remove("/repo/a.rs").is_none());
+    }
+
+    #[test]
+    fn test_corrupt_manifest_is_rebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let mut manifest = Manifest::load(&path);
+        assert!(!manifest.is_unchanged("/repo/a.rs", "abc"));
+        manifest.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_save_merges_changes_saved_by_another_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let mut seed = Manifest::load(&path);
+        seed.record("/repo/old.rs".to_string(), entry("old"));
+        seed.save().unwrap();
+
+        // Two manifests loaded before either saves, like a watcher and an index run
+        let mut watcher = Manifest::load(&path);
+        let mut index = Manifest::load(&path);
+        watcher.record("/repo/a.rs".to_string(), entry("abc"));
+        watcher.remove("/repo/old.rs");
+        index.record("/repo/b.rs".to_string(), entry("def"));
+        watcher.save().unwrap();
+        index.save().unwrap();
+
+        let merged = Manifest::load(&path);
+        assert_eq!(merged.entries().keys().collect::<Vec<_>>(), vec!["/repo/a.rs", "/repo/b.rs"]);
+        assert_eq!(index.entries(), merged.entries());
+        assert!(!dir.path().join("manifest.json.lock").exists());
+
+        // Clearing drops what others saved as well
+        index.clear();
+        index.save().unwrap();
+        assert!(Manifest::load(&path).entries().is_empty());
+    }
+
+    #[test]
+    fn test_save_replaces_stale_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let lock = dir.path().join("manifest.json.lock");
+        std::fs::write(&lock, "").unwrap();
+        let old = SystemTime::now() - STALE_LOCK_AGE * 2;
+        std::fs::File::options().write(true).open(&lock).unwrap().set_modified(old).unwrap();
+
+        let mut manifest = Manifest::load(&path);
+        manifest.record("/repo/a.rs".to_string(), entry("abc"));
+        manifest.save().unwrap();
+        assert!(Manifest::load(&path).is_unchanged("/repo/a.rs", "abc"));
+        assert!(!lock.exists());
+    }
+
+    #[test]
+    fn test_manifest_with_chunk_ids_still_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        std::fs::write(&path, r#"{"/repo/a.rs":{"hash":"abc","chunk_ids":["a#1-2"]}}"#).unwrap();
+
+        let manifest = Manifest::load(&path);
+        assert!(manifest.is_unchanged("/repo/a.rs", "abc"));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod api;
 pub mod config;
 pub mod hashing;
+pub mod manifest;
+pub mod memory;
+pub mod paths;
@@ -0,0 +1,51 @@
+use std::path::Path;
+
+/// Normalize a file path into the name the server indexes it under.
+///
+/// Resolves to an absolute path when the file exists, strips the Windows
+/// `\\?\` UNC prefix and uses forward slashes, so the same file always maps
+/// to the same name regardless of how it was reached. For a file that no
+/// longer exists the parent directory is resolved instead, so deleted files
+/// still map to the name they were indexed under.
+pub fn upload_name(path: &Path) -> String {
+    let abs_path = std::fs::canonicalize(path)
+        .or_else(|err| match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+                std::fs::canonicalize(parent).map(|dir| dir.join(name))
+            }
+            _ => Err(err),
+        })
+        .unwrap_or_else(|_| path.to_path_buf());
+    let abs_str = abs_path.to_string_lossy();
+    let clean_path = abs_str.strip_prefix("\\\\?\\").unwrap_or(&abs_str);
+    clean_path.replace("\\", "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deleted_file_keeps_its_upload_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("gone.rs");
+        std::fs::write(&file, "fn main() {}").unwrap();
+
+        let indexed = upload_name(&file);
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(upload_name(&file), indexed);
+    }
+
+    #[test]
+    fn test_equivalent_paths_share_a_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("same.rs");
+        std::fs::write(&file, "").unwrap();
+
+        let dotted = dir.path().join(".").join("same.rs");
+        assert_eq!(upload_name(&dotted), upload_name(&file));
+        assert!(!upload_name(&file).contains('\\'));
+    }
+}
@@ -4,7 +4,12 @@ mod watcher;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use commands::{search, watch};
+use colored::*;
+use commands::{clear, manifest, reindex, search, status, watch};
+use watcher::progress::Progress;
+
+/// Text analyzers the BM25 service can create an index with
+const TOKENIZERS: [&str; 3] = ["code", "code_stem", "default"];
 
 #[derive(Parser)]
 #[command(name = "ricesearch")]
@@ -12,23 +17,47 @@ use commands::{search, watch};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Disable colored output (also honored: the NO_COLOR environment variable)
+    #[arg(long, global = true, default_value_t = false)]
+    no_color: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Watch a directory and index changes
     Watch {
-        /// Directory to watch
+        /// Directories to watch
         #[arg(default_value = ".")]
-        path: String,
+        paths: Vec<String>,
 
-        /// Organization ID (optional scope)
+        /// Organization ID to scope to (default: the `store` config key, or RICE_STORE)
         #[arg(short, long)]
         org_id: Option<String>,
 
         /// Perform full initial index
         #[arg(long, short = 'f', default_value_t = false)]
         full_index: bool,
+
+        /// Milliseconds a file must stay unchanged before it is re-indexed
+        #[arg(
+            long,
+            default_value_t = watch::DEFAULT_DEBOUNCE_MS,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        debounce: u64,
+
+        /// Only list what the initial full index would upload, then exit
+        #[arg(long, default_value_t = false, requires = "full_index")]
+        dry_run: bool,
+
+        /// Follow symlinked directories during the initial full index
+        #[arg(long, default_value_t = false)]
+        follow_symlinks: bool,
+
+        /// Print per-file progress as colored text or as JSON lines
+        #[arg(long, value_enum, default_value_t = Progress::Text)]
+        progress: Progress,
     },
 
     /// Search indexed code
@@ -43,6 +72,15 @@ enum Commands {
         /// Output as JSON
         #[arg(long, default_value_t = false)]
         json: bool,
+
+        /// Organization ID to search within (default: the `store` config key, or RICE_STORE)
+        #[arg(short, long)]
+        org_id: Option<String>,
+
+        /// Fail unless the BM25 index uses this text analyzer, so queries and
+        /// indexed code are tokenized alike
+        #[arg(long, value_parser = TOKENIZERS)]
+        tokenizer: Option<String>,
     },
 
     /// Index a directory once (no watch)
@@ -50,6 +88,87 @@ enum Commands {
         /// Directory to index
         #[arg(default_value = ".")]
         path: String,
+
+        /// Organization ID to index into (default: the `store` config key, or RICE_STORE)
+        #[arg(short, long)]
+        org_id: Option<String>,
+
+        /// Report peak memory usage when indexing finishes
+        #[arg(long, default_value_t = false)]
+        profile_memory: bool,
+
+        /// List the files that would be uploaded without uploading them
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+
+        /// Descend into symlinked directories (cycles are detected and skipped)
+        #[arg(long, default_value_t = false)]
+        follow_symlinks: bool,
+
+        /// Number of files to upload concurrently
+        #[arg(short = 'j', long, default_value_t = watcher::scanner::DEFAULT_SCAN_CONCURRENCY)]
+        concurrency: usize,
+
+        /// Print per-file progress as colored text or as JSON lines
+        #[arg(long, value_enum, default_value_t = Progress::Text)]
+        progress: Progress,
+
+        /// Fail unless the BM25 index uses this text analyzer, so queries and
+        /// indexed code are tokenized alike
+        #[arg(long, value_parser = TOKENIZERS)]
+        tokenizer: Option<String>,
+    },
+
+    /// Re-scan a directory: index new and changed files, drop deleted ones
+    Reindex {
+        /// Directory to reconcile with the index
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Organization ID to reconcile (default: the `store` config key, or RICE_STORE)
+        #[arg(short, long)]
+        org_id: Option<String>,
+
+        /// List what would be uploaded and deleted without changing the index
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+
+        /// Descend into symlinked directories (cycles are detected and skipped)
+        #[arg(long, default_value_t = false)]
+        follow_symlinks: bool,
+
+        /// Number of files to upload concurrently
+        #[arg(short = 'j', long, default_value_t = watcher::scanner::DEFAULT_SCAN_CONCURRENCY)]
+        concurrency: usize,
+
+        /// Print per-file progress as colored text or as JSON lines
+        #[arg(long, value_enum, default_value_t = Progress::Text)]
+        progress: Progress,
+    },
+
+    /// Show backend index stats, or what is indexed for a single file
+    Status {
+        /// File to look up (omit for overall index stats)
+        path: Option<String>,
+
+        /// Organization ID the file was indexed under (default: the `store` config key, or RICE_STORE)
+        #[arg(short, long)]
+        org_id: Option<String>,
+
+        /// Output as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Delete everything indexed for an org
+    Clear {
+        /// Organization ID whose index is cleared (default: the `store` config key, or RICE_STORE)
+        #[arg(short, long)]
+        org_id: Option<String>,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long, default_value_t = false)]
+        yes: bool,
     },
 
     /// Manage configuration
@@ -57,6 +176,22 @@ enum Commands {
         #[command(subcommand)]
         action: ConfigAction,
     },
+
+    /// Inspect the record of uploaded files and their chunks
+    Manifest {
+        #[command(subcommand)]
+        action: ManifestAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ManifestAction {
+    /// Dump the manifest
+    Show {
+        /// Output as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -69,42 +204,133 @@ enum ConfigAction {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // Every print site goes through `colored`, so this one switch covers them all;
+    // log lines are styled by env_logger and are switched off alongside
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let no_color = cli.no_color || no_color_env;
+    if no_color {
+        colored::control::set_override(false);
+    }
+
     // env_logger::init(); // Ensure not initialized twice if we move it or use another logger config
     if std::env::var("RUST_LOG").is_err() {
         std::env::set_var("RUST_LOG", "info");
     }
-    env_logger::init();
-
-    let cli = Cli::parse();
+    let mut logger = env_logger::Builder::from_default_env();
+    if no_color {
+        logger.write_style(env_logger::WriteStyle::Never);
+    }
+    logger.init();
 
     match &cli.command {
         Commands::Watch {
-            path,
+            paths,
             org_id,
             full_index,
+            debounce,
+            dry_run,
+            follow_symlinks,
+            progress,
         } => {
-            watch::run(path, org_id.clone(), *full_index).await?;
+            let debounce = std::time::Duration::from_millis(*debounce);
+            let options = watch::ScanOptions {
+                full_index: *full_index,
+                dry_run: *dry_run,
+                follow_symlinks: *follow_symlinks,
+                progress: *progress,
+            };
+            watch::run(paths, org_id.clone(), debounce, options).await?;
         }
-        Commands::Search { query, limit, json } => {
-            search::run(query, *limit, *json).await?;
+        Commands::Search {
+            query,
+            limit,
+            json,
+            org_id,
+            tokenizer,
+        } => {
+            search::run(query, *limit, *json, org_id.as_deref(), tokenizer.as_deref()).await?;
         }
-        Commands::Index { path } => {
+        Commands::Index {
+            path,
+            org_id,
+            profile_memory,
+            dry_run,
+            follow_symlinks,
+            concurrency,
+            progress,
+            tokenizer,
+        } => {
             // Re-use watch logic but exit after initial scan?
             // Or explicit scan function.
             // For MVP re-use logic part or just scan:
             // Let's call the scanner directly for Index
             let config = core::config::load_config()?;
-            let client = core::api::ApiClient::new(&config.backend_url);
-            let scanner = watcher::scanner::Scanner::new(client, "public".to_string());
-            scanner.scan(std::path::Path::new(path)).await;
+            let client = core::api::ApiClient::from_config(&config);
+            if let Some(tokenizer) = tokenizer {
+                client.require_tokenizer(tokenizer).await?;
+            }
+            let manifest = core::manifest::Manifest::load(&core::manifest::manifest_path());
+            let org_id = config.org_id(org_id.as_deref());
+            let scanner = watcher::scanner::Scanner::new(client, org_id, manifest.shared())
+                .with_concurrency(*concurrency)
+                .with_dry_run(*dry_run)
+                .with_follow_symlinks(*follow_symlinks)
+                .with_progress(*progress)
+                .with_global_ignore(Some(core::config::global_ignore_path()))
+                .with_filter(watcher::filter::FileFilter::from_config(&config));
+            let stats = scanner.scan(std::path::Path::new(path)).await;
+
+            if *profile_memory {
+                let peak = core::memory::peak_rss_bytes()
+                    .map(core::memory::format_bytes)
+                    .unwrap_or_else(|| "unavailable".to_string());
+                progress.status(&format!(
+                    "{} {} files in {:.2}s, peak memory {}",
+                    "[PROFILE]".cyan(),
+                    stats.indexed,
+                    stats.duration.as_secs_f64(),
+                    peak.bold()
+                ));
+            }
+        }
+        Commands::Reindex {
+            path,
+            org_id,
+            dry_run,
+            follow_symlinks,
+            concurrency,
+            progress,
+        } => {
+            let options = reindex::ReindexOptions {
+                dry_run: *dry_run,
+                follow_symlinks: *follow_symlinks,
+                concurrency: *concurrency,
+                progress: *progress,
+            };
+            reindex::run(path, org_id.as_deref(), options).await?;
+        }
+        Commands::Status { path, org_id, json } => {
+            status::run(path.as_deref(), org_id.as_deref(), *json).await?;
         }
+        Commands::Clear { org_id, yes } => {
+            clear::run(org_id.as_deref(), *yes).await?;
+        }
+        Commands::Manifest { action } => match action {
+            ManifestAction::Show { json } => manifest::show(*json)?,
+        },
         Commands::Config { action } => match action {
             ConfigAction::Show => {
                 let c = core::config::load_config()?;
                 println!("{:#?}", c);
             }
             ConfigAction::Set { key, value } => {
-                println!("Set {} = {} (Not implemented persistence yet)", key, value)
+                let path = core::config::config_path();
+                let mut c = core::config::load_config_file(&path)?;
+                c.set(key, value)?;
+                core::config::save_config(&c, &path)?;
+                println!("{} Set {} = {} ({})", "✓".green(), key, value, path.display());
             }
         },
     }
@@ -0,0 +1,107 @@
+use crate::core::config::AppConfig;
+use crate::core::memory::format_bytes;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a file is inspected for NUL bytes when sniffing for binary content
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Why a file was left out of the index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    TooLarge(u64),
+    Binary,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::TooLarge(size) => write!(f, "too large: {}", format_bytes(*size)),
+            SkipReason::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// Decides which files are worth uploading, before they are hashed or read in full
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    max_bytes: u64,
+    allow_extensions: Vec<String>,
+}
+
+impl FileFilter {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            max_bytes: config.max_file_bytes,
+            allow_extensions: config.allow_extensions.clone(),
+        }
+    }
+
+    /// Check a file, returning why it should be skipped if it shouldn't be indexed
+    ///
+    /// Allowlisted suffixes bypass both the size limit and the binary sniff.
+    pub fn check(&self, path: &Path) -> Option<SkipReason> {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        if self.allow_extensions.iter().any(|ext| name.ends_with(ext.as_str())) {
+            return None;
+        }
+
+        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size > self.max_bytes {
+            return Some(SkipReason::TooLarge(size));
+        }
+
+        if looks_binary(path) {
+            return Some(SkipReason::Binary);
+        }
+        None
+    }
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        Self::from_config(&AppConfig::default())
+    }
+}
+
+/// A NUL byte near the start of a file is a reliable sign it isn't text
+fn looks_binary(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    match file.take(SNIFF_BYTES as u64).read_to_end(&mut head) {
+        Ok(_) => head.contains(&0),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skips_binary_and_oversized_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("main.rs");
+        let binary = dir.path().join("logo.png");
+        let large = dir.path().join("bundle.js");
+        let minified = dir.path().join("vendor.min.js");
+        std::fs::write(&text, "fn main() {}").unwrap();
+        std::fs::write(&binary, [0x89, b'P', b'N', b'G', 0, 0, 0, 13]).unwrap();
+        std::fs::write(&large, "x".repeat(64)).unwrap();
+        std::fs::write(&minified, "x".repeat(64)).unwrap();
+
+        let filter = FileFilter {
+            max_bytes: 32,
+            allow_extensions: vec![".min.js".to_string()],
+        };
+
+        assert_eq!(filter.check(&text), None);
+        assert_eq!(filter.check(&binary), Some(SkipReason::Binary));
+        assert_eq!(filter.check(&large), Some(SkipReason::TooLarge(64)));
+        assert_eq!(filter.check(&minified), None);
+    }
+}
@@ -1 +1,3 @@
+pub mod filter;
+pub mod progress;
 pub mod scanner;
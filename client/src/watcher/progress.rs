@@ -0,0 +1,129 @@
+use crate::core::memory::format_bytes;
+use colored::*;
+use serde::Serialize;
+
+/// How per-file progress is written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Progress {
+    /// Colored lines meant for a terminal
+    #[default]
+    Text,
+    /// One JSON object per line, for tools driving the client
+    Json,
+}
+
+/// Something that happened to a single file, or the end of a scan
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    Indexing { path: &'a str },
+    Indexed { path: &'a str, bytes: u64 },
+    WouldIndex { path: &'a str, bytes: u64 },
+    Skipped { path: &'a str, reason: String },
+    Deleted { path: &'a str },
+    WouldDelete { path: &'a str },
+    Error { path: &'a str, error: String },
+    Summary {
+        indexed: usize,
+        skipped: usize,
+        errored: usize,
+        bytes: u64,
+        duration_ms: u64,
+        dry_run: bool,
+    },
+    Reconciled {
+        added: usize,
+        updated: usize,
+        removed: usize,
+        skipped: usize,
+        errored: usize,
+        duration_ms: u64,
+        dry_run: bool,
+    },
+}
+
+impl Progress {
+    pub fn is_json(self) -> bool {
+        self == Progress::Json
+    }
+
+    /// Print a per-file event
+    ///
+    /// Summaries are printed by [`ScanStats`](super::scanner::ScanStats) and
+    /// [`ReconcileStats`](super::scanner::ReconcileStats), which have their own
+    /// text layouts.
+    pub fn emit(self, event: &ProgressEvent) {
+        match self {
+            Progress::Json => {
+                // Start markers only help someone watching a terminal
+                if !matches!(event, ProgressEvent::Indexing { .. }) {
+                    println!("{}", event.to_json());
+                }
+            }
+            Progress::Text => match event {
+                ProgressEvent::Indexing { path } => println!("{} {}", "[INDEXING]".blue(), path),
+                ProgressEvent::Indexed { path, .. } => println!("{} {}", "[OK]".green(), path),
+                ProgressEvent::WouldIndex { path, bytes } => {
+                    println!("{} {} ({})", "[WOULD INDEX]".cyan(), path, format_bytes(*bytes))
+                }
+                ProgressEvent::Skipped { path, reason } => {
+                    println!("{} {} ({})", "[SKIP]".dimmed(), path, reason)
+                }
+                ProgressEvent::Deleted { path } => println!("{} {}", "[DELETED]".yellow(), path),
+                ProgressEvent::WouldDelete { path } => {
+                    println!("{} {}", "[WOULD DELETE]".cyan(), path)
+                }
+                ProgressEvent::Error { path, error } => {
+                    println!("{} {} ({})", "[ERROR]".red(), path, error)
+                }
+                ProgressEvent::Summary { .. } | ProgressEvent::Reconciled { .. } => {}
+            },
+        }
+    }
+
+    /// Print a status line that isn't about a file; kept off stdout in JSON mode
+    pub fn status(self, message: &str) {
+        match self {
+            Progress::Text => println!("{}", message),
+            Progress::Json => eprintln!("{}", message),
+        }
+    }
+}
+
+impl ProgressEvent<'_> {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("progress events always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_serialize_with_event_tag() {
+        let indexed = ProgressEvent::Indexed { path: "src/main.rs", bytes: 42 };
+        assert_eq!(
+            indexed.to_json(),
+            r#"{"event":"indexed","path":"src/main.rs","bytes":42}"#
+        );
+
+        let skipped = ProgressEvent::Skipped { path: "a.bin", reason: "binary".to_string() };
+        assert_eq!(
+            skipped.to_json(),
+            r#"{"event":"skipped","path":"a.bin","reason":"binary"}"#
+        );
+
+        let summary = ProgressEvent::Summary {
+            indexed: 1,
+            skipped: 2,
+            errored: 0,
+            bytes: 42,
+            duration_ms: 5,
+            dry_run: false,
+        };
+        let value: serde_json::Value = serde_json::from_str(&summary.to_json()).unwrap();
+        assert_eq!(value["event"], "summary");
+        assert_eq!(value["skipped"], 2);
+    }
+}
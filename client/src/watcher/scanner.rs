@@ -1,70 +1,586 @@
-use crate::core::api::ApiClient;
+use crate::core::api::{ApiClient, FileDeletion};
+use crate::core::hashing::compute_file_hash;
+use crate::core::manifest::{ManifestEntry, SharedManifest};
+use crate::core::memory::format_bytes;
+use crate::core::paths::upload_name;
+use super::filter::FileFilter;
+use super::progress::{Progress, ProgressEvent};
+use anyhow::Result;
 use colored::*;
+use futures::stream::{self, StreamExt};
 use ignore::WalkBuilder;
 use log::{debug, info, warn};
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default number of files uploaded concurrently during a scan
+pub const DEFAULT_SCAN_CONCURRENCY: usize = 8;
 
 pub struct Scanner {
     client: ApiClient,
     org_id: String,
+    manifest: SharedManifest,
+    concurrency: usize,
+    filter: FileFilter,
+    dry_run: bool,
+    follow_symlinks: bool,
+    progress: Progress,
+    /// Ignore file applied beneath each tree's own ignore files
+    global_ignore: Option<PathBuf>,
+}
+
+/// What happened to a single file during a scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileOutcome {
+    Indexed { bytes: u64 },
+    Skipped,
+    Failed,
+}
+
+/// Totals for one scan, printed as a summary when it finishes
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScanStats {
+    pub indexed: usize,
+    pub skipped: usize,
+    pub errored: usize,
+    /// Size of the files that were uploaded
+    pub bytes: u64,
+    pub duration: Duration,
+}
+
+impl ScanStats {
+    fn record(&mut self, outcome: FileOutcome) {
+        match outcome {
+            FileOutcome::Indexed { bytes } => {
+                self.indexed += 1;
+                self.bytes += bytes;
+            }
+            FileOutcome::Skipped => self.skipped += 1,
+            FileOutcome::Failed => self.errored += 1,
+        }
+    }
+
+    fn print_summary(&self, dry_run: bool, progress: Progress) {
+        if progress.is_json() {
+            let summary = ProgressEvent::Summary {
+                indexed: self.indexed,
+                skipped: self.skipped,
+                errored: self.errored,
+                bytes: self.bytes,
+                duration_ms: self.duration.as_millis() as u64,
+                dry_run,
+            };
+            println!("{}", summary.to_json());
+            return;
+        }
+
+        if dry_run {
+            println!(
+                "{} {} files would be uploaded ({}), {} skipped",
+                "[DRY RUN]".cyan().bold(),
+                self.indexed.to_string().bold(),
+                format_bytes(self.bytes),
+                self.skipped
+            );
+            return;
+        }
+
+        let errored = format!("{} errored", self.errored);
+        println!(
+            "{} {} indexed, {} skipped, {} ({}) in {:.2}s",
+            "[DONE]".green().bold(),
+            self.indexed.to_string().bold(),
+            self.skipped,
+            if self.errored > 0 { errored.red() } else { errored.normal() },
+            format_bytes(self.bytes),
+            self.duration.as_secs_f64()
+        );
+    }
+}
+
+/// Totals for one reconciliation of the index with the filesystem
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReconcileStats {
+    /// Files uploaded that the index didn't have
+    pub added: usize,
+    /// Indexed files uploaded again because their content changed
+    pub updated: usize,
+    /// Indexed paths deleted because their file is gone
+    pub removed: usize,
+    pub skipped: usize,
+    pub errored: usize,
+    pub duration: Duration,
+}
+
+impl ReconcileStats {
+    fn record(&mut self, known: bool, outcome: FileOutcome) {
+        match outcome {
+            FileOutcome::Indexed { .. } if known => self.updated += 1,
+            FileOutcome::Indexed { .. } => self.added += 1,
+            FileOutcome::Skipped => self.skipped += 1,
+            FileOutcome::Failed => self.errored += 1,
+        }
+    }
+
+    fn print_summary(&self, dry_run: bool, progress: Progress) {
+        if progress.is_json() {
+            let summary = ProgressEvent::Reconciled {
+                added: self.added,
+                updated: self.updated,
+                removed: self.removed,
+                skipped: self.skipped,
+                errored: self.errored,
+                duration_ms: self.duration.as_millis() as u64,
+                dry_run,
+            };
+            println!("{}", summary.to_json());
+            return;
+        }
+
+        let label = if dry_run { "[DRY RUN]".cyan().bold() } else { "[DONE]".green().bold() };
+        let errored = format!("{} errored", self.errored);
+        println!(
+            "{} {} added, {} updated, {} removed, {} unchanged or skipped, {} in {:.2}s",
+            label,
+            self.added.to_string().bold(),
+            self.updated.to_string().bold(),
+            self.removed.to_string().bold(),
+            self.skipped,
+            if self.errored > 0 { errored.red() } else { errored.normal() },
+            self.duration.as_secs_f64()
+        );
+    }
 }
 
 impl Scanner {
-    pub fn new(client: ApiClient, org_id: String) -> Self {
-        Self { client, org_id }
+    /// Scan into `org_id`, recording uploads in `manifest`
+    pub fn new(client: ApiClient, org_id: String, manifest: SharedManifest) -> Self {
+        Self {
+            client,
+            org_id,
+            manifest,
+            concurrency: DEFAULT_SCAN_CONCURRENCY,
+            filter: FileFilter::default(),
+            dry_run: false,
+            follow_symlinks: false,
+            progress: Progress::Text,
+            global_ignore: None,
+        }
+    }
+
+    /// The manifest this scanner records uploads in, to share with a watcher
+    pub fn manifest(&self) -> SharedManifest {
+        self.manifest.clone()
     }
 
-    pub async fn scan(&self, path: &Path) {
+    /// Apply a machine-wide ignore file beneath each tree's own ignore files
+    pub fn with_global_ignore(mut self, global_ignore: Option<PathBuf>) -> Self {
+        self.global_ignore = global_ignore;
+        self
+    }
+
+    /// Write per-file progress as colored text or JSON lines
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Descend into symlinked directories, visiting each real directory once
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Report what would be uploaded without contacting the backend
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Use `filter` to decide which files are too large or binary to upload
+    pub fn with_filter(mut self, filter: FileFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Upload up to `concurrency` files at once (at least one)
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Index every file under `path` and print a summary of what happened
+    ///
+    /// Files whose content hash matches the last successful upload are skipped.
+    /// In a dry run, files that would be uploaded are counted as indexed.
+    pub async fn scan(&self, path: &Path) -> ScanStats {
+        let started = Instant::now();
         // Use the path as provided (relative) - WalkBuilder handles gitignore properly
         info!("Starting initial scan of: {:?}", path);
+        let files = self.collect_files(path);
+
+        let mut stats = stream::iter(&files)
+            .map(|file| self.process_file(file, false))
+            .buffer_unordered(self.concurrency)
+            .fold(ScanStats::default(), |mut stats, outcome| {
+                stats.record(outcome);
+                std::future::ready(stats)
+            })
+            .await;
+
+        self.save_manifest();
+        info!("Scan complete.");
+        stats.duration = started.elapsed();
+        stats.print_summary(self.dry_run, self.progress);
+        stats
+    }
+
+    /// Bring what is indexed under `path` in line with the files on disk
+    ///
+    /// New files are uploaded, changed ones re-uploaded and indexed paths under
+    /// `path` whose file is gone (or now ignored) are deleted. A file missing
+    /// from the index is uploaded even if the hash cache says it is unchanged.
+    /// In a dry run nothing is uploaded or deleted.
+    pub async fn reconcile(&self, path: &Path) -> Result<ReconcileStats> {
+        let started = Instant::now();
+        info!("Reconciling index with: {:?}", path);
+
+        // Only paths under this root are ours to delete
+        let root = format!("{}/", upload_name(path).trim_end_matches('/'));
+        let indexed: HashSet<String> = self
+            .client
+            .list_paths(&self.org_id)
+            .await?
+            .into_iter()
+            .filter(|indexed| indexed.starts_with(&root))
+            .collect();
+
+        let files: Vec<(PathBuf, String)> = self
+            .collect_files(path)
+            .into_iter()
+            .map(|file| {
+                let name = upload_name(&file);
+                (file, name)
+            })
+            .collect();
+
+        let mut stats = stream::iter(&files)
+            .map(|(file, name)| async {
+                let known = indexed.contains(name);
+                (known, self.process_file(file, !known).await)
+            })
+            .buffer_unordered(self.concurrency)
+            .fold(ReconcileStats::default(), |mut stats, (known, outcome)| {
+                stats.record(known, outcome);
+                std::future::ready(stats)
+            })
+            .await;
+
+        let on_disk: HashSet<&str> = files.iter().map(|(_, name)| name.as_str()).collect();
+        let mut gone: Vec<&String> =
+            indexed.iter().filter(|name| !on_disk.contains(name.as_str())).collect();
+        gone.sort();
+        for name in gone {
+            if self.dry_run {
+                self.progress.emit(&ProgressEvent::WouldDelete { path: name });
+                stats.removed += 1;
+                continue;
+            }
+            match self.client.delete_file(name, &self.org_id).await {
+                Ok(FileDeletion::Deleted { .. }) => {
+                    self.progress.emit(&ProgressEvent::Deleted { path: name });
+                    self.manifest.lock().unwrap().remove(name);
+                    stats.removed += 1;
+                }
+                Ok(FileDeletion::NotIndexed) => {
+                    // Removed since it was listed
+                    self.manifest.lock().unwrap().remove(name);
+                }
+                Err(e) => {
+                    self.progress.emit(&ProgressEvent::Error { path: name, error: e.to_string() });
+                    stats.errored += 1;
+                }
+            }
+        }
+
+        self.save_manifest();
+        stats.duration = started.elapsed();
+        stats.print_summary(self.dry_run, self.progress);
+        Ok(stats)
+    }
+
+    fn save_manifest(&self) {
+        if let Err(e) = self.manifest.lock().unwrap().save() {
+            warn!("Could not save manifest: {}", e);
+        }
+    }
 
-        let walker = WalkBuilder::new(path)
+    /// Walk `path` with the ignore rules applied, returning each file to index once
+    fn collect_files(&self, path: &Path) -> Vec<PathBuf> {
+        // Real directories already walked; when following symlinks a link back
+        // to one of them (a cycle, or a second route to the same tree) is skipped
+        let follow_symlinks = self.follow_symlinks;
+        let visited_dirs: Arc<Mutex<HashSet<PathBuf>>> = Arc::default();
+        if let Ok(root) = std::fs::canonicalize(path) {
+            visited_dirs.lock().unwrap().insert(root);
+        }
+
+        let mut builder = WalkBuilder::new(path);
+        // Global ignores have the lowest precedence, so local files can re-include with `!`
+        if let Some(global_ignore) = self.global_ignore.as_deref().filter(|p| p.is_file()) {
+            if let Some(err) = builder.add_ignore(global_ignore) {
+                warn!("Error reading {}: {}", global_ignore.display(), err);
+            }
+        }
+
+        let walker = builder
             .hidden(false) 
             .ignore(true)        // Respect .ignore files
             .git_ignore(true)    // Respect .gitignore
             .add_custom_ignore_filename(".riceignore")
-            .filter_entry(|entry| {
+            .follow_links(follow_symlinks)
+            .filter_entry(move |entry| {
                 // Only filter .git explicitly, let gitignore handle the rest
-                entry.file_name() != ".git"
+                if entry.file_name() == ".git" {
+                    return false;
+                }
+                if !follow_symlinks || !entry.file_type().is_some_and(|t| t.is_dir()) {
+                    return true;
+                }
+                let Ok(real_dir) = std::fs::canonicalize(entry.path()) else {
+                    return true;
+                };
+                if visited_dirs.lock().unwrap().insert(real_dir.clone()) {
+                    true
+                } else {
+                    warn!(
+                        "Skipping {}: {} was already visited (symlink cycle?)",
+                        entry.path().display(),
+                        real_dir.display()
+                    );
+                    false
+                }
             })
             .build();
 
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut seen_files = HashSet::new();
         for result in walker {
             match result {
                 Ok(entry) => {
-                    let entry_path = entry.path();
-                    if entry_path.is_file() {
-                        self.process_file(entry_path).await;
+                    if !entry.path().is_file() {
+                        continue;
+                    }
+                    // Several links may lead to the same file; upload it once
+                    if follow_symlinks {
+                        let real_file = std::fs::canonicalize(entry.path())
+                            .unwrap_or_else(|_| entry.path().to_path_buf());
+                        if !seen_files.insert(real_file) {
+                            continue;
+                        }
                     }
+                    files.push(entry.into_path());
                 }
                 Err(err) => warn!("Error walking path: {}", err),
             }
         }
-        info!("Scan complete.");
+        files
     }
 
-    async fn process_file(&self, path: &Path) {
+    /// Upload one file unless it is filtered out or, without `force`, unchanged
+    async fn process_file(&self, path: &Path, force: bool) -> FileOutcome {
         // Get relative path for display
         let rel_display = path.to_string_lossy().replace("\\", "/");
         debug!("Processing: {}", rel_display);
 
         // Only resolve to absolute when sending to server
         let abs_path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
-        
-        // Clean UNC prefix for server
-        let abs_str = abs_path.to_string_lossy();
-        let clean_path = if abs_str.starts_with("\\\\?\\") {
-            &abs_str[4..]
-        } else {
-            &abs_str
-        };
-        let upload_name = clean_path.replace("\\", "/");
+        let upload_name = upload_name(&abs_path);
+
+        let path = rel_display.as_str();
+        let progress = self.progress;
+
+        if let Some(reason) = self.filter.check(&abs_path) {
+            progress.emit(&ProgressEvent::Skipped { path, reason: reason.to_string() });
+            return FileOutcome::Skipped;
+        }
 
-        println!("{} {}", "[INDEXING]".blue(), rel_display);
+        // An unreadable file still gets an upload attempt so the error is reported
+        let hash = compute_file_hash(&abs_path).ok();
+        if let Some(hash) = hash.as_ref().filter(|_| !force) {
+            if self.manifest.lock().unwrap().is_unchanged(&upload_name, hash) {
+                let reason = "unchanged".to_string();
+                progress.emit(&ProgressEvent::Skipped { path, reason });
+                return FileOutcome::Skipped;
+            }
+        }
+
+        let bytes = std::fs::metadata(&abs_path).map(|m| m.len()).unwrap_or(0);
+        if self.dry_run {
+            progress.emit(&ProgressEvent::WouldIndex { path, bytes });
+            return FileOutcome::Indexed { bytes };
+        }
+
+        progress.emit(&ProgressEvent::Indexing { path });
 
         match self.client.index_file(&abs_path, &upload_name, &self.org_id).await {
-            Ok(_) => println!("{} {}", "[OK]".green(), rel_display),
-            Err(e) => println!("{} {} ({})", "[ERROR]".red(), rel_display, e),
+            Ok(_) => {
+                progress.emit(&ProgressEvent::Indexed { path, bytes });
+                if let Some(hash) = hash {
+                    let entry = ManifestEntry { hash };
+                    self.manifest.lock().unwrap().record(upload_name, entry);
+                }
+                FileOutcome::Indexed { bytes }
+            }
+            Err(e) => {
+                progress.emit(&ProgressEvent::Error { path, error: e.to_string() });
+                FileOutcome::Failed
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::AppConfig;
+    use crate::core::manifest::Manifest;
+
+    /// A scanner for `backend_url` with its manifest in `state`, never the user's
+    fn scanner(backend_url: &str, state: &Path) -> Scanner {
+        let config = AppConfig {
+            backend_url: backend_url.to_string(),
+            ..Default::default()
+        };
+        let manifest = Manifest::load(&state.join("manifest.json")).shared();
+        Scanner::new(ApiClient::from_config(&config), "public".to_string(), manifest)
+    }
+
+    #[test]
+    fn test_scan_stats_record() {
+        let mut stats = ScanStats::default();
+        stats.record(FileOutcome::Indexed { bytes: 100 });
+        stats.record(FileOutcome::Indexed { bytes: 50 });
+        stats.record(FileOutcome::Skipped);
+        stats.record(FileOutcome::Failed);
+
+        assert_eq!(stats.indexed, 2);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.errored, 1);
+        assert_eq!(stats.bytes, 150);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_applies_skip_rules_without_uploading() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        // .gitignore is only honored inside a git repository
+        std::fs::create_dir(root.join(".git")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("debug.log"), "ignored").unwrap();
+        std::fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+
+        // Nothing listens here, so any upload attempt would be counted as an error
+        let state = tempfile::tempdir().unwrap();
+        let scanner = scanner("http://127.0.0.1:9", state.path()).with_dry_run(true);
+        let stats = scanner.scan(root).await;
+
+        // .gitignore and main.rs would be uploaded; the PNG is binary; *.log is ignored
+        assert_eq!(stats.indexed, 2);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.errored, 0);
+    }
+
+    #[tokio::test]
+    async fn test_global_ignore_layers_under_local_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let global_ignore = dir.path().join("ignore");
+        std::fs::write(&global_ignore, "*.log\nnode_modules/\n").unwrap();
+
+        let root = dir.path().join("repo");
+        std::fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        std::fs::write(root.join("node_modules/pkg/index.js"), "module.exports = 1").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("debug.log"), "ignored").unwrap();
+        std::fs::write(root.join("keep.log"), "re-included").unwrap();
+        std::fs::write(root.join(".riceignore"), "!keep.log\n").unwrap();
+
+        let state = tempfile::tempdir().unwrap();
+        let scanner = scanner("http://127.0.0.1:9", state.path())
+            .with_dry_run(true)
+            .with_global_ignore(Some(global_ignore));
+        let stats = scanner.scan(&root).await;
+
+        // main.rs, keep.log and .riceignore itself
+        assert_eq!(stats.indexed, 3);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_dry_run_classifies_files() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("repo");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("kept.rs"), "fn kept() {}").unwrap();
+        std::fs::write(root.join("new.rs"), "fn new() {}").unwrap();
+        let root_name = upload_name(&root);
+
+        // The index knows kept.rs, a file since deleted, and a file outside the root
+        let body = serde_json::json!({
+            "files": [
+                format!("{}/kept.rs", root_name),
+                format!("{}/gone.rs", root_name),
+                format!("{}-other/elsewhere.rs", root_name),
+            ],
+            "count": 3
+        })
+        .to_string();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let state = tempfile::tempdir().unwrap();
+        let scanner = scanner(&url, state.path()).with_dry_run(true);
+        let stats = scanner.reconcile(&root).await.unwrap();
+
+        assert_eq!(stats.added, 1);
+        assert_eq!(stats.updated, 1);
+        assert_eq!(stats.removed, 1);
+        assert_eq!(stats.errored, 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_follow_symlinks_skips_cycles() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub fn lib() {}").unwrap();
+        // A cycle back to the root and a second route into src/
+        std::os::unix::fs::symlink(root, root.join("src/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("src"), root.join("alias")).unwrap();
+
+        let state = tempfile::tempdir().unwrap();
+        let scanner = scanner("http://127.0.0.1:9", state.path())
+            .with_dry_run(true)
+            .with_follow_symlinks(true);
+        let stats = scanner.scan(root).await;
+
+        assert_eq!(stats.indexed, 1);
+    }
+}
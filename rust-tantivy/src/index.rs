@@ -2,14 +2,33 @@
 //! 
 //! Handles creation, modification, and persistence of the BM25 index.
 
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
 use tantivy::{
+    collector::{Count, DocSetCollector, TopDocs},
     directory::MmapDirectory,
-    schema::{Schema, Value, STORED, STRING, TEXT},
-    Index, IndexWriter, TantivyDocument,
+    query::{
+        AllQuery, BooleanQuery, ConstScoreQuery, EmptyQuery, FuzzyTermQuery, Occur, Query,
+        PhraseQuery, QueryParser, QueryParserError, TermQuery,
+    },
+    postings::Postings,
+    schema::{
+        Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, INDEXED, STORED,
+        STRING,
+    },
+    time::format_description::well_known::Rfc3339,
+    tokenizer::TextAnalyzer,
+    DateTime, DocAddress, DocId, DocSet, Index, IndexReader, IndexWriter, ReloadPolicy, Score,
+    Searcher, SegmentReader, TantivyDocument, Term, TERMINATED,
 };
 use thiserror::Error;
 
+use crate::search::{dedup_by_path, Conjunction, MatchMode, MinMatch, SearchConfig};
+use crate::tokenizer::{self, TextTokenizer};
+
 /// Errors that can occur during index operations
 #[derive(Error, Debug)]
 pub enum IndexError {
@@ -24,19 +43,234 @@ pub enum IndexError {
     
     #[error("Directory error: {0}")]
     Directory(#[from] tantivy::directory::error::OpenDirectoryError),
+    
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+    
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+}
+
+/// Tantivy's per-thread writer arena must be at least 15MB and below 4GB
+const WRITER_HEAP_MIN_BYTES: usize = 15_000_000;
+const WRITER_HEAP_MAX_BYTES: usize = u32::MAX as usize - 1_000_000;
+
+/// IndexWriter sizing
+#[derive(Debug, Clone, Copy)]
+pub struct WriterConfig {
+    /// Total writer heap in MB, shared across indexing threads
+    pub heap_mb: usize,
+    /// Number of indexing threads; `None` lets Tantivy pick from the CPU count
+    pub num_threads: Option<usize>,
+}
+
+impl Default for WriterConfig {
+    fn default() -> Self {
+        Self {
+            heap_mb: 50,
+            num_threads: None,
+        }
+    }
+}
+
+impl WriterConfig {
+    /// Read `TANTIVY_WRITER_HEAP_MB` and `TANTIVY_WRITER_THREADS`, falling back to defaults
+    pub fn from_env() -> Result<Self, IndexError> {
+        let mut config = Self::default();
+        
+        if let Ok(value) = std::env::var("TANTIVY_WRITER_HEAP_MB") {
+            config.heap_mb = value.parse().map_err(|_| {
+                IndexError::Config(format!(
+                    "TANTIVY_WRITER_HEAP_MB must be a number, got {:?}",
+                    value
+                ))
+            })?;
+        }
+        if let Ok(value) = std::env::var("TANTIVY_WRITER_THREADS") {
+            let threads = value.parse().map_err(|_| {
+                IndexError::Config(format!(
+                    "TANTIVY_WRITER_THREADS must be a number, got {:?}",
+                    value
+                ))
+            })?;
+            config.num_threads = Some(threads);
+        }
+        
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Check the heap fits Tantivy's per-thread arena limits
+    pub fn validate(&self) -> Result<(), IndexError> {
+        let heap_bytes = self.heap_mb.saturating_mul(1_000_000);
+        
+        let Some(threads) = self.num_threads else {
+            // Tantivy reduces the thread count itself when the heap is small
+            if heap_bytes < WRITER_HEAP_MIN_BYTES {
+                return Err(IndexError::Config(format!(
+                    "writer heap of {}MB is below Tantivy's 15MB minimum",
+                    self.heap_mb
+                )));
+            }
+            return Ok(());
+        };
+        
+        if threads == 0 {
+            return Err(IndexError::Config("writer threads must be at least 1".to_string()));
+        }
+        let per_thread = heap_bytes / threads;
+        if per_thread < WRITER_HEAP_MIN_BYTES {
+            return Err(IndexError::Config(format!(
+                "writer heap of {}MB over {} threads is below Tantivy's 15MB per-thread minimum",
+                self.heap_mb, threads
+            )));
+        }
+        if per_thread >= WRITER_HEAP_MAX_BYTES {
+            return Err(IndexError::Config(format!(
+                "writer heap of {}MB over {} threads exceeds Tantivy's 4GB per-thread maximum",
+                self.heap_mb, threads
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Store that chunks belong to when none is given
+pub const DEFAULT_STORE: &str = "public";
+
+/// Source metadata stored alongside a chunk's text
+#[derive(Debug, Clone, Default)]
+pub struct ChunkMetadata {
+    /// Store (namespace) the chunk belongs to; empty means [`DEFAULT_STORE`]
+    pub store: String,
+    pub path: String,
+    pub language: String,
+    pub start_line: u64,
+    pub end_line: u64,
+}
+
+/// Ranked page of doc addresses, total match count and best score overall
+type Collected = (Vec<(Score, DocAddress)>, usize, Option<Score>);
+
+/// A page of ranked hits and the number of chunks the query matched overall
+#[derive(Debug, Clone)]
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    /// Every matching chunk, ignoring `limit`, `offset` and `min_score`
+    pub total_hits: usize,
+    /// Score of the best match overall, even when it is on an earlier page
+    pub top_score: Option<Score>,
+}
+
+/// A single ranked search result
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub chunk_id: String,
+    pub score: f32,
+    pub metadata: ChunkMetadata,
+    /// Line of the first matched term, set when `highlight` is requested
+    pub match_line: Option<u64>,
+}
+
+/// Stored information about a single indexed chunk
+#[derive(Debug, Clone)]
+pub struct ChunkInfo {
+    pub chunk_id: String,
+    pub start_line: u64,
+    pub end_line: u64,
+    /// RFC 3339 timestamp, absent for chunks indexed before it was tracked
+    pub indexed_at: Option<String>,
+}
+
+/// A chunk's stored text and metadata
+#[derive(Debug, Clone)]
+pub struct StoredChunk {
+    pub chunk_id: String,
+    /// Empty for indexes created before text was stored
+    pub text: String,
+    pub metadata: ChunkMetadata,
+    pub indexed_at: Option<String>,
+}
+
+/// Locates the line of a hit's text where the query first matches
+///
+/// The text is re-tokenized with the field's analyzer and compared against the
+/// query's terms. Fuzzy matches only count when the term matches exactly.
+struct LineFinder {
+    analyzer: TextAnalyzer,
+    terms: BTreeSet<String>,
+    /// Match tokens starting with a term rather than equal to it
+    prefix: bool,
+}
+
+impl LineFinder {
+    /// `start_line` plus the number of lines before the first matching token
+    fn find(&mut self, content: &str, start_line: u64) -> Option<u64> {
+        let mut stream = self.analyzer.token_stream(content);
+        while let Some(token) = stream.next() {
+            let matched = if self.prefix {
+                self.terms.iter().any(|term| token.text.starts_with(term.as_str()))
+            } else {
+                self.terms.contains(&token.text)
+            };
+            if matched {
+                let lines_before = content[..token.offset_from].matches('\n').count();
+                return Some(start_line + lines_before as u64);
+            }
+        }
+        None
+    }
 }
 
 /// Wrapper around Tantivy index for BM25 search
+///
+/// Every method takes `&self`: only the writer sits behind a lock, so
+/// searches run against the reader while adds, deletes or a commit are in
+/// progress.
 pub struct TantivyIndex {
     index: Index,
-    writer: IndexWriter,
-    chunk_id_field: tantivy::schema::Field,
-    text_field: tantivy::schema::Field,
+    writer: Mutex<IndexWriter>,
+    /// Long-lived reader; reloaded after our own commits and on external ones
+    reader: IndexReader,
+    chunk_id_field: Field,
+    text_field: Field,
+    // Optional so indexes created before these fields existed still open
+    text_words_field: Option<Field>,
+    text_cased_field: Option<Field>,
+    store_field: Option<Field>,
+    path_field: Option<Field>,
+    language_field: Option<Field>,
+    start_line_field: Option<Field>,
+    end_line_field: Option<Field>,
+    indexed_at_field: Option<Field>,
+    content_hash_field: Option<Field>,
+    /// Adds and deletes staged since the last commit
+    pending_ops: AtomicUsize,
+    /// Chunk ids with an add or delete staged since the last commit; only
+    /// locked while holding the writer
+    staged_ids: Mutex<HashSet<String>>,
 }
 
 impl TantivyIndex {
     /// Create or open a Tantivy index at the specified path
+    ///
+    /// Writer sizing and the text tokenizer come from the environment, see
+    /// [`WriterConfig::from_env`] and [`TextTokenizer::from_env`].
     pub fn new(data_dir: &str) -> Result<Self, IndexError> {
+        Self::with_options(data_dir, WriterConfig::from_env()?, TextTokenizer::from_env()?)
+    }
+    
+    /// Create or open a Tantivy index with explicit writer sizing and tokenizer
+    ///
+    /// The tokenizer only applies when the index is created; an existing index
+    /// keeps the one recorded in its schema.
+    pub fn with_options(
+        data_dir: &str,
+        writer_config: WriterConfig,
+        tokenizer: TextTokenizer,
+    ) -> Result<Self, IndexError> {
+        writer_config.validate()?;
+        
         let path = Path::new(data_dir);
         
         // Create directory if it doesn't exist
@@ -44,8 +278,32 @@ impl TantivyIndex {
         
         // Build schema
         let mut schema_builder = Schema::builder();
-        let chunk_id_field = schema_builder.add_text_field("chunk_id", STRING | STORED);
-        let text_field = schema_builder.add_text_field("text", TEXT);
+        schema_builder.add_text_field("chunk_id", STRING | STORED);
+        let text_indexing = TextFieldIndexing::default()
+            .set_tokenizer(tokenizer.name())
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        schema_builder.add_text_field(
+            "text",
+            TextOptions::default().set_indexing_options(text_indexing).set_stored(),
+        );
+        // Whole-word copies of `text` for `whole_word` and `case_sensitive` searches.
+        // Frequencies only: positions would make each copy about as large as
+        // `text`, and phrases are only matched against `text`
+        for (name, analyzer) in [("text_words", "default"), ("text_cased", tokenizer::CASED)] {
+            let indexing = TextFieldIndexing::default()
+                .set_tokenizer(analyzer)
+                .set_index_option(IndexRecordOption::WithFreqs);
+            schema_builder
+                .add_text_field(name, TextOptions::default().set_indexing_options(indexing));
+        }
+        schema_builder.add_text_field("store", STRING | STORED);
+        schema_builder.add_text_field("path", STRING | STORED);
+        schema_builder.add_text_field("language", STRING | STORED);
+        // Indexed so queries can range over them, e.g. `start_line:[1 TO 50]`
+        schema_builder.add_u64_field("start_line", INDEXED | STORED);
+        schema_builder.add_u64_field("end_line", INDEXED | STORED);
+        schema_builder.add_date_field("indexed_at", STORED);
+        schema_builder.add_text_field("content_hash", STRING | STORED);
         let schema = schema_builder.build();
         
         // Open or create index
@@ -58,71 +316,546 @@ impl TantivyIndex {
             let dir = MmapDirectory::open(path)?;
             Index::create(dir, schema.clone(), tantivy::IndexSettings::default())?
         };
+        tokenizer::register(index.tokenizers());
+        
+        // Resolve fields from the on-disk schema, which may predate newer fields
+        let schema = index.schema();
+        let chunk_id_field = schema.get_field("chunk_id")?;
+        let text_field = schema.get_field("text")?;
+        let text_words_field = schema.get_field("text_words").ok();
+        let text_cased_field = schema.get_field("text_cased").ok();
+        let store_field = schema.get_field("store").ok();
+        let path_field = schema.get_field("path").ok();
+        let language_field = schema.get_field("language").ok();
+        let start_line_field = schema.get_field("start_line").ok();
+        let end_line_field = schema.get_field("end_line").ok();
+        let indexed_at_field = schema.get_field("indexed_at").ok();
+        let content_hash_field = schema.get_field("content_hash").ok();
+        
+        let heap_bytes = writer_config.heap_mb * 1_000_000;
+        let writer = match writer_config.num_threads {
+            Some(threads) => index.writer_with_num_threads(threads, heap_bytes)?,
+            None => index.writer(heap_bytes)?,
+        };
         
-        // Create writer with 50MB buffer
-        let writer = index.writer(50_000_000)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .try_into()?;
         
         Ok(Self {
             index,
-            writer,
+            writer: Mutex::new(writer),
+            reader,
             chunk_id_field,
             text_field,
+            text_words_field,
+            text_cased_field,
+            store_field,
+            path_field,
+            language_field,
+            start_line_field,
+            end_line_field,
+            indexed_at_field,
+            content_hash_field,
+            pending_ops: AtomicUsize::new(0),
+            staged_ids: Mutex::default(),
         })
     }
     
+    /// Lock the writer; a panic mid-write leaves it usable, so poisoning is ignored
+    fn writer(&self) -> MutexGuard<'_, IndexWriter> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    
+    fn staged_ids(&self) -> MutexGuard<'_, HashSet<String>> {
+        self.staged_ids.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    
     /// Add a document to the index (not committed until commit() is called)
-    pub fn add_document(&mut self, chunk_id: &str, text: &str) -> Result<(), IndexError> {
-        // Delete existing document with same chunk_id first
-        self.delete_document(chunk_id)?;
+    ///
+    /// Returns `false` without touching the index when the committed chunk
+    /// already has the same text and metadata, so re-sending unchanged
+    /// content causes no delete, re-tokenizing or commit churn.
+    pub fn add_document(
+        &self,
+        chunk_id: &str,
+        text: &str,
+        metadata: &ChunkMetadata,
+    ) -> Result<bool, IndexError> {
+        let content_hash = content_hash(text, metadata);
+        let writer = self.writer();
+        let mut staged_ids = self.staged_ids();
+        // A staged add or delete would override the committed copy, so only
+        // an untouched chunk can be compared against it
+        if !staged_ids.contains(chunk_id) {
+            if let Some(field) = self.content_hash_field {
+                let searcher = self.reader.searcher();
+                if let Some(doc) = self.find_by_chunk_id(&searcher, chunk_id)? {
+                    let stored = doc.get_first(field).and_then(|v| v.as_str());
+                    if stored == Some(content_hash.as_str()) {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
         
         let mut doc = TantivyDocument::default();
         doc.add_text(self.chunk_id_field, chunk_id);
         doc.add_text(self.text_field, text);
+        for field in [self.text_words_field, self.text_cased_field].into_iter().flatten() {
+            doc.add_text(field, text);
+        }
+        if let Some(field) = self.store_field {
+            let store = if metadata.store.is_empty() { DEFAULT_STORE } else { &metadata.store };
+            doc.add_text(field, store);
+        }
+        if let Some(field) = self.path_field {
+            doc.add_text(field, &metadata.path);
+        }
+        if let Some(field) = self.language_field {
+            doc.add_text(field, &metadata.language);
+        }
+        if let Some(field) = self.start_line_field {
+            doc.add_u64(field, metadata.start_line);
+        }
+        if let Some(field) = self.end_line_field {
+            doc.add_u64(field, metadata.end_line);
+        }
+        if let Some(field) = self.indexed_at_field {
+            doc.add_date(field, DateTime::from_utc(tantivy::time::OffsetDateTime::now_utc()));
+        }
+        if let Some(field) = self.content_hash_field {
+            doc.add_text(field, &content_hash);
+        }
         
-        self.writer.add_document(doc)?;
-        Ok(())
+        // Delete existing document with same chunk_id first
+        let term = Term::from_field_text(self.chunk_id_field, chunk_id);
+        writer.delete_term(term);
+        writer.add_document(doc)?;
+        staged_ids.insert(chunk_id.to_string());
+        self.pending_ops.fetch_add(1, Ordering::SeqCst);
+        Ok(true)
     }
     
     /// Delete a document by chunk_id
-    pub fn delete_document(&mut self, chunk_id: &str) -> Result<(), IndexError> {
-        let term = tantivy::Term::from_field_text(self.chunk_id_field, chunk_id);
-        self.writer.delete_term(term);
+    pub fn delete_document(&self, chunk_id: &str) -> Result<(), IndexError> {
+        let term = Term::from_field_text(self.chunk_id_field, chunk_id);
+        let writer = self.writer();
+        writer.delete_term(term);
+        self.staged_ids().insert(chunk_id.to_string());
+        self.pending_ops.fetch_add(1, Ordering::SeqCst);
         Ok(())
     }
     
+    /// Delete every chunk whose id starts with `prefix`, returning how many matched
+    ///
+    /// When `store` is given, chunks belonging to other stores are left alone.
+    /// Staged changes are committed first so chunks added since the last
+    /// commit are found by the term dictionary scan.
+    pub fn delete_prefix(&self, prefix: &str, store: Option<&str>) -> Result<usize, IndexError> {
+        let mut writer = self.writer();
+        if self.pending_ops() > 0 {
+            self.commit_with(&mut writer)?;
+        }
+        
+        let mut chunk_ids = self.chunk_ids_with_prefix(prefix)?;
+        if let Some(store) = store {
+            let searcher = self.reader.searcher();
+            let mut in_store = Vec::with_capacity(chunk_ids.len());
+            for chunk_id in chunk_ids {
+                if let Some(doc) = self.find_by_chunk_id(&searcher, &chunk_id)? {
+                    if self.read_metadata(&doc).store == store {
+                        in_store.push(chunk_id);
+                    }
+                }
+            }
+            chunk_ids = in_store;
+        }
+        
+        let mut staged_ids = self.staged_ids();
+        for chunk_id in &chunk_ids {
+            writer.delete_term(Term::from_field_text(self.chunk_id_field, chunk_id));
+            staged_ids.insert(chunk_id.clone());
+        }
+        self.pending_ops.fetch_add(chunk_ids.len(), Ordering::SeqCst);
+        Ok(chunk_ids.len())
+    }
+    
+    /// Number of staged adds/deletes not yet visible to searches
+    pub fn pending_ops(&self) -> usize {
+        self.pending_ops.load(Ordering::SeqCst)
+    }
+    
     /// Commit pending changes to disk
-    pub fn commit(&mut self) -> Result<(), IndexError> {
-        self.writer.commit()?;
+    pub fn commit(&self) -> Result<(), IndexError> {
+        self.commit_with(&mut self.writer())
+    }
+    
+    /// Commit through an already locked writer
+    fn commit_with(&self, writer: &mut IndexWriter) -> Result<(), IndexError> {
+        writer.commit()?;
+        self.pending_ops.store(0, Ordering::SeqCst);
+        self.staged_ids().clear();
+        // Make the commit visible now rather than after the reload delay
+        self.reader.reload()?;
         Ok(())
     }
     
+    /// Commit, then merge every searchable segment into one
+    ///
+    /// Merging drops deleted documents, so a lone segment is rewritten too
+    /// when it holds any. The writer stays locked until the merge is done and
+    /// visible, so no commit can add segments or deletes it would miss; writes
+    /// wait meanwhile. Returns `false` when there was nothing to merge.
+    pub fn optimize(&self) -> Result<bool, IndexError> {
+        let mut writer = self.writer();
+        self.commit_with(&mut writer)?;
+        let segment_ids = self.index.searchable_segment_ids()?;
+        if segment_ids.len() < 2 && self.deleted_doc_count() == 0 {
+            return Ok(false);
+        }
+        writer.merge(&segment_ids).wait()?;
+        self.reader.reload()?;
+        Ok(true)
+    }
+    
     /// Clear the entire index
-    pub fn clear(&mut self) -> Result<(), IndexError> {
-        self.writer.delete_all_documents()?;
-        self.writer.commit()?;
-        Ok(())
+    pub fn clear(&self) -> Result<(), IndexError> {
+        let mut writer = self.writer();
+        writer.delete_all_documents()?;
+        self.commit_with(&mut writer)
+    }
+    
+    /// Delete every document in one store and commit, returning how many were removed
+    pub fn clear_store(&self, store: &str) -> Result<usize, IndexError> {
+        let mut writer = self.writer();
+        if self.pending_ops() > 0 {
+            self.commit_with(&mut writer)?;
+        }
+        
+        let query = self.restrict_to_stores(Box::new(AllQuery), &[store]);
+        let removed = self.reader.searcher().search(&query, &Count)?;
+        writer.delete_query(query)?;
+        self.commit_with(&mut writer)?;
+        Ok(removed)
     }
     
     /// Get the number of documents in the index
     pub fn doc_count(&self) -> u64 {
-        let reader = self.index.reader().ok();
-        reader.map(|r| r.searcher().num_docs()).unwrap_or(0)
+        self.reader.searcher().num_docs()
+    }
+    
+    /// Name of the analyzer the `text` field was created with
+    pub fn tokenizer_name(&self) -> String {
+        match self.index.schema().get_field_entry(self.text_field).field_type() {
+            tantivy::schema::FieldType::Str(options) => options
+                .get_indexing_options()
+                .map(|indexing| indexing.tokenizer().to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+    
+    /// Documents deleted or replaced but still taking space until their segment is merged
+    pub fn deleted_doc_count(&self) -> u64 {
+        let searcher = self.reader.searcher();
+        searcher.segment_readers().iter().map(|r| r.num_deleted_docs() as u64).sum()
+    }
+    
+    /// Number of segments in the committed index
+    pub fn segment_count(&self) -> usize {
+        self.reader.searcher().segment_readers().len()
+    }
+    
+    /// Count live documents per store
+    ///
+    /// Indexes created before stores existed report everything under [`DEFAULT_STORE`].
+    pub fn store_doc_counts(&self) -> Result<BTreeMap<String, u64>, IndexError> {
+        let searcher = self.reader.searcher();
+        let mut counts = BTreeMap::new();
+        
+        let Some(store_field) = self.store_field else {
+            counts.insert(DEFAULT_STORE.to_string(), searcher.num_docs());
+            return Ok(counts);
+        };
+        
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(store_field)?;
+            let mut stream = inverted_index.terms().stream()?;
+            
+            while stream.advance() {
+                let term_info = stream.value();
+                let live_docs = match segment_reader.alive_bitset() {
+                    Some(bitset) => inverted_index
+                        .read_postings_from_terminfo(term_info, IndexRecordOption::Basic)?
+                        .doc_freq_given_deletes(bitset),
+                    None => term_info.doc_freq,
+                };
+                if live_docs > 0 {
+                    let store = String::from_utf8_lossy(stream.key()).into_owned();
+                    *counts.entry(store).or_insert(0) += u64::from(live_docs);
+                }
+            }
+        }
+        
+        Ok(counts)
+    }
+    
+    /// List the live chunk ids starting with `prefix`, in sorted order
+    ///
+    /// Walks the `chunk_id` term dictionary of every segment rather than
+    /// running a query, so no stored documents are loaded.
+    pub fn chunk_ids_with_prefix(&self, prefix: &str) -> Result<Vec<String>, IndexError> {
+        let searcher = self.reader.searcher();
+        let mut chunk_ids = BTreeSet::new();
+        
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(self.chunk_id_field)?;
+            let mut stream = inverted_index.terms().range().ge(prefix.as_bytes()).into_stream()?;
+            
+            while stream.advance() {
+                if !stream.key().starts_with(prefix.as_bytes()) {
+                    break;
+                }
+                // Terms of deleted documents linger until merge, so check liveness
+                let term_info = stream.value();
+                let postings = inverted_index
+                    .read_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+                let alive = match segment_reader.alive_bitset() {
+                    Some(bitset) => postings.doc_freq_given_deletes(bitset) > 0,
+                    None => true,
+                };
+                if alive {
+                    chunk_ids.insert(String::from_utf8_lossy(stream.key()).into_owned());
+                }
+            }
+        }
+        
+        Ok(chunk_ids.into_iter().collect())
+    }
+    
+    /// List the distinct file paths with live chunks, in sorted order
+    ///
+    /// Paths are read from the `path` term dictionary, so they don't depend on
+    /// what the chunk ids look like (the backend uses UUIDs). Chunks indexed
+    /// without a path fall back to the part of a `path#start-end` id before
+    /// its suffix; other ids name no path. With `store`, only chunks whose
+    /// documents belong to that store count.
+    pub fn indexed_paths(&self, store: Option<&str>) -> Result<Vec<String>, IndexError> {
+        let searcher = self.reader.searcher();
+        let mut paths = BTreeSet::new();
+        
+        for segment_reader in searcher.segment_readers() {
+            let in_store = match (store, self.store_field) {
+                (Some(store), Some(store_field)) => {
+                    let term = Term::from_field_text(store_field, store);
+                    Some(self.docs_with_term(segment_reader, term)?)
+                }
+                // Without a store field every document is implicitly in the default store
+                (Some(store), None) if store != DEFAULT_STORE => continue,
+                _ => None,
+            };
+            let listed = |doc: DocId| {
+                segment_reader.alive_bitset().is_none_or(|b| b.is_alive(doc))
+                    && in_store.as_ref().is_none_or(|docs| docs.contains(&doc))
+            };
+            
+            // Documents that named their path; their chunk ids are not consulted
+            let mut with_path = HashSet::new();
+            if let Some(path_field) = self.path_field {
+                let inverted_index = segment_reader.inverted_index(path_field)?;
+                let mut stream = inverted_index.terms().stream()?;
+                while stream.advance() {
+                    if stream.key().is_empty() {
+                        continue;
+                    }
+                    let mut postings = inverted_index
+                        .read_postings_from_terminfo(stream.value(), IndexRecordOption::Basic)?;
+                    let mut matched = false;
+                    while postings.doc() != TERMINATED {
+                        let doc = postings.doc();
+                        if listed(doc) {
+                            with_path.insert(doc);
+                            matched = true;
+                        }
+                        postings.advance();
+                    }
+                    if matched {
+                        paths.insert(String::from_utf8_lossy(stream.key()).into_owned());
+                    }
+                }
+            }
+            
+            let inverted_index = segment_reader.inverted_index(self.chunk_id_field)?;
+            let mut stream = inverted_index.terms().stream()?;
+            while stream.advance() {
+                let chunk_id = String::from_utf8_lossy(stream.key());
+                let Some((path, _)) = chunk_id.rsplit_once('#') else {
+                    continue;
+                };
+                let mut postings = inverted_index
+                    .read_postings_from_terminfo(stream.value(), IndexRecordOption::Basic)?;
+                let mut matched = false;
+                while postings.doc() != TERMINATED {
+                    let doc = postings.doc();
+                    if listed(doc) && !with_path.contains(&doc) {
+                        matched = true;
+                        break;
+                    }
+                    postings.advance();
+                }
+                if matched {
+                    paths.insert(path.to_string());
+                }
+            }
+        }
+        
+        Ok(paths.into_iter().collect())
+    }
+    
+    fn docs_with_term(
+        &self,
+        segment_reader: &SegmentReader,
+        term: Term,
+    ) -> Result<HashSet<DocId>, IndexError> {
+        let inverted_index = segment_reader.inverted_index(term.field())?;
+        let mut docs = HashSet::new();
+        if let Some(mut postings) =
+            inverted_index.read_postings(&term, IndexRecordOption::Basic)?
+        {
+            while postings.doc() != TERMINATED {
+                docs.insert(postings.doc());
+                postings.advance();
+            }
+        }
+        Ok(docs)
     }
     
-    /// Search for documents using BM25
-    pub fn search(&self, query_str: &str, limit: usize) -> Result<Vec<(String, f32)>, IndexError> {
-        use tantivy::collector::TopDocs;
-        use tantivy::query::QueryParser;
+    /// List the chunks indexed for a file path
+    ///
+    /// Matches the stored `path` field, so it works whatever the chunk ids
+    /// look like (the backend uses UUIDs). Chunks indexed without a path but
+    /// with a `path#start-end` id are found by that prefix too. Returns an
+    /// empty list when the path is not indexed.
+    pub fn chunks_for_path(&self, path: &str) -> Result<Vec<ChunkInfo>, IndexError> {
+        let searcher = self.reader.searcher();
+        let mut chunks = BTreeMap::new();
+        
+        if let Some(path_field) = self.path_field {
+            let term = Term::from_field_text(path_field, path);
+            let query = TermQuery::new(term, IndexRecordOption::Basic);
+            for address in searcher.search(&query, &DocSetCollector)? {
+                let doc: TantivyDocument = searcher.doc(address)?;
+                let Some(chunk_id) = doc.get_first(self.chunk_id_field).and_then(|v| v.as_str())
+                else {
+                    continue;
+                };
+                let metadata = self.read_metadata(&doc);
+                let chunk = ChunkInfo {
+                    chunk_id: chunk_id.to_string(),
+                    start_line: metadata.start_line,
+                    end_line: metadata.end_line,
+                    indexed_at: self.read_indexed_at(&doc),
+                };
+                chunks.insert(chunk.chunk_id.clone(), chunk);
+            }
+        }
+        
+        let prefix = format!("{}#", path);
+        for chunk_id in self.chunk_ids_with_prefix(&prefix)? {
+            if chunks.contains_key(&chunk_id) {
+                continue;
+            }
+            let Some(doc) = self.find_by_chunk_id(&searcher, &chunk_id)? else {
+                continue;
+            };
+            let (start_line, end_line) = parse_line_range(&chunk_id[prefix.len()..]);
+            let chunk = ChunkInfo {
+                chunk_id,
+                start_line,
+                end_line,
+                indexed_at: self.read_indexed_at(&doc),
+            };
+            chunks.insert(chunk.chunk_id.clone(), chunk);
+        }
         
-        let reader = self.index.reader()?;
-        let searcher = reader.searcher();
+        let mut chunks: Vec<ChunkInfo> = chunks.into_values().collect();
+        chunks.sort_by_key(|chunk| (chunk.start_line, chunk.end_line));
+        Ok(chunks)
+    }
+    
+    /// Fetch a single chunk's stored text and metadata, if it is indexed
+    pub fn get_document(&self, chunk_id: &str) -> Result<Option<StoredChunk>, IndexError> {
+        let searcher = self.reader.searcher();
+        let Some(doc) = self.find_by_chunk_id(&searcher, chunk_id)? else {
+            return Ok(None);
+        };
+        Ok(Some(self.read_stored_chunk(&doc)))
+    }
+    
+    /// Visit every live chunk, or those in `store`, until `visit` returns `false`
+    ///
+    /// Walks each segment's live doc ids, so deleted documents awaiting a
+    /// merge are skipped and only one stored document is held at a time.
+    pub fn for_each_chunk(
+        &self,
+        store: Option<&str>,
+        mut visit: impl FnMut(StoredChunk) -> bool,
+    ) -> Result<(), IndexError> {
+        let searcher = self.reader.searcher();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                let address = DocAddress::new(segment_ord as u32, doc_id);
+                let chunk = self.read_stored_chunk(&searcher.doc(address)?);
+                if store.is_some_and(|store| chunk.metadata.store != store) {
+                    continue;
+                }
+                if !visit(chunk) {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Search for documents using BM25, skipping the first `config.offset` ranked hits
+    ///
+    /// Every match is counted in the same pass, so the total agrees with the page.
+    pub fn search(
+        &self,
+        query_str: &str,
+        config: &SearchConfig,
+    ) -> Result<SearchPage, IndexError> {
+        let searcher = self.reader.searcher();
+        let query = self.filtered_query(query_str, config)?;
         
-        // Build query parser for text field
-        let query_parser = QueryParser::for_index(&self.index, vec![self.text_field]);
-        let query = query_parser.parse_query(query_str)?;
+        // Collapsing duplicate paths shrinks the page, so over-fetch first;
+        // without it every filter is in the query and exactly a page is fetched
+        let overfetch;
+        let collect_config = if config.dedup_by_path {
+            let page_end = config.offset.checked_add(config.limit).ok_or_else(|| {
+                IndexError::InvalidQuery(format!("offset {} is too large", config.offset))
+            })?;
+            overfetch = SearchConfig {
+                limit: page_end.saturating_mul(config.overfetch),
+                offset: 0,
+                ..config.clone()
+            };
+            &overfetch
+        } else {
+            config
+        };
         
         // Execute search
-        let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
+        let collected = self.top_docs(&searcher, query.as_ref(), collect_config)?;
+        let (top_docs, total_hits, top_score) = collected;
+        
+        let mut line_finder = if config.highlight {
+            Some(self.line_finder(query.as_ref(), query_str, config)?)
+        } else {
+            None
+        };
         
         // Extract results
         let mut results = Vec::with_capacity(top_docs.len());
@@ -131,45 +864,1551 @@ impl TantivyIndex {
             if let Some(chunk_id_value) = doc.get_first(self.chunk_id_field) {
                 // Extract string from CompactDocValue (Tantivy 0.25+)
                 if let Some(text) = chunk_id_value.as_str() {
-                    results.push((text.to_string(), score));
+                    let metadata = self.read_metadata(&doc);
+                    let match_line = line_finder.as_mut().and_then(|finder| {
+                        let content = doc.get_first(self.text_field)?.as_str()?;
+                        finder.find(content, metadata.start_line)
+                    });
+                    results.push(SearchHit {
+                        chunk_id: text.to_string(),
+                        score,
+                        metadata,
+                        match_line,
+                    });
+                }
+            }
+        }
+        
+        if config.dedup_by_path {
+            results = dedup_by_path(results)
+                .into_iter()
+                .skip(config.offset)
+                .take(config.limit)
+                .collect();
+        }
+        
+        Ok(SearchPage { hits: results, total_hits, top_score })
+    }
+    
+    /// Collect the analyzed query terms hits are scanned for by [`LineFinder`]
+    fn line_finder(
+        &self,
+        query: &dyn Query,
+        query_str: &str,
+        config: &SearchConfig,
+    ) -> Result<LineFinder, IndexError> {
+        let field = self.query_field(config)?;
+        let mut analyzer = self.index.tokenizer_for_field(field)?;
+        let mut terms = BTreeSet::new();
+        if config.match_mode == MatchMode::Standard {
+            query.query_terms(&mut |term, _| {
+                if term.field() == field {
+                    if let Some(text) = term.value().as_str() {
+                        terms.insert(text.to_string());
+                    }
+                }
+            });
+        } else {
+            let mut stream = analyzer.token_stream(query_str);
+            while let Some(token) = stream.next() {
+                terms.insert(token.text.clone());
+            }
+        }
+        
+        Ok(LineFinder {
+            analyzer,
+            terms,
+            prefix: config.match_mode == MatchMode::Prefix,
+        })
+    }
+    
+    /// Count every chunk matching a search, ignoring `limit`, `offset` and `min_score`
+    pub fn count(&self, query_str: &str, config: &SearchConfig) -> Result<usize, IndexError> {
+        let query = self.filtered_query(query_str, config)?;
+        Ok(self.reader.searcher().search(query.as_ref(), &Count)?)
+    }
+    
+    /// Parse a query and apply the store and language restrictions of `config`
+    fn filtered_query(
+        &self,
+        query_str: &str,
+        config: &SearchConfig,
+    ) -> Result<Box<dyn Query>, IndexError> {
+        let mut query = self.build_query(query_str, config)?;
+        if let Some(min_match) = config.min_match {
+            query = require_min_match(query, min_match)?;
+        }
+        
+        let stores: Vec<&str> =
+            config.store.iter().chain(&config.stores).map(String::as_str).collect();
+        if !stores.is_empty() {
+            query = self.restrict_to_stores(query, &stores);
+        }
+        if !config.languages.is_empty() {
+            query = self.restrict_to_languages(query, &config.languages);
+        }
+        Ok(query)
+    }
+    
+    /// Collect the ranked page of hits, the total match count and the best
+    /// score overall, rescoring when custom BM25 parameters are set
+    fn top_docs(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        config: &SearchConfig,
+    ) -> Result<Collected, IndexError> {
+        let page = || TopDocs::with_limit(config.limit).and_offset(config.offset);
+        let top_score = |best: Vec<(Score, DocAddress)>| best.first().map(|(score, _)| *score);
+        let builtin = |searcher: &Searcher| -> Result<_, IndexError> {
+            let (hits, count, best) =
+                searcher.search(query, &(page(), Count, TopDocs::with_limit(1)))?;
+            Ok((hits, count, top_score(best)))
+        };
+        if !config.has_custom_bm25() {
+            return builtin(searcher);
+        }
+        
+        // Tantivy's BM25 constants are fixed, so recompute the score of every
+        // match from its term frequencies and field lengths instead. Terms of
+        // qualified clauses like `language:rust` count too, each normalized by
+        // the length of its own field.
+        let mut terms: Vec<Term> = Vec::new();
+        query.query_terms(&mut |term, _| {
+            if !terms.contains(term) {
+                terms.push(term.clone());
+            }
+        });
+        
+        let total_docs: u64 = searcher.segment_readers().iter().map(|r| r.max_doc() as u64).sum();
+        if terms.is_empty() || total_docs == 0 {
+            // e.g. range queries, which match without terms
+            return builtin(searcher);
+        }
+        
+        let mut weighted_terms = Vec::with_capacity(terms.len());
+        for term in terms {
+            let mut total_tokens = 0;
+            for segment_reader in searcher.segment_readers() {
+                total_tokens += segment_reader.inverted_index(term.field())?.total_num_tokens();
+            }
+            // Fields without lengths, such as line numbers, count one token
+            let average_fieldnorm = if total_tokens == 0 {
+                1.0
+            } else {
+                total_tokens as Score / total_docs as Score
+            };
+            let doc_freq = searcher.doc_freq(&term)? as Score;
+            let idf = (1.0 + (total_docs as Score - doc_freq + 0.5) / (doc_freq + 0.5)).ln();
+            weighted_terms.push((term, idf, average_fieldnorm));
+        }
+        
+        let (k1, b) = (config.bm25_k1, config.bm25_b);
+        let rescore = move |segment_reader: &SegmentReader| {
+            let mut postings: Vec<_> = weighted_terms
+                .iter()
+                .filter_map(|(term, idf, average_fieldnorm)| {
+                    let postings = segment_reader
+                        .inverted_index(term.field())
+                        .ok()?
+                        .read_postings(term, IndexRecordOption::WithFreqs)
+                        .ok()??;
+                    let fieldnorms = segment_reader.get_fieldnorms_reader(term.field()).ok();
+                    Some((*idf, *average_fieldnorm, fieldnorms, postings))
+                })
+                .collect();
+            
+            move |doc: DocId, _original: Score| {
+                // Matches arrive in doc id order, so each postings list only moves forward
+                let mut score = 0.0;
+                for (idf, average_fieldnorm, fieldnorms, term_postings) in &mut postings {
+                    if term_postings.doc() < doc {
+                        term_postings.seek(doc);
+                    }
+                    if term_postings.doc() == doc {
+                        let fieldnorm = fieldnorms.as_ref().map_or(1, |f| f.fieldnorm(doc));
+                        let length_norm =
+                            k1 * (1.0 - b + b * fieldnorm as Score / *average_fieldnorm);
+                        let tf = term_postings.term_freq() as Score;
+                        score += *idf * tf * (k1 + 1.0) / (tf + length_norm);
+                    }
+                }
+                score
+            }
+        };
+        
+        let best = TopDocs::with_limit(1).tweak_score(rescore.clone());
+        let (hits, count, best) =
+            searcher.search(query, &(page().tweak_score(rescore), Count, best))?;
+        Ok((hits, count, top_score(best)))
+    }
+    
+    /// Build the text query for the configured match mode
+    ///
+    /// In standard mode unqualified terms search `text`, or the whole-word copy
+    /// picked by [`Self::query_field`], while `field:term` targets any indexed
+    /// field: `language:rust`, `path:"src/lib.rs"`, `store:acme`,
+    /// `chunk_id:"a.rs#1-9"` or `start_line:[1 TO 50]`. The line fields are
+    /// only indexed in indexes created since they became queryable.
+    fn build_query(
+        &self,
+        query_str: &str,
+        config: &SearchConfig,
+    ) -> Result<Box<dyn Query>, IndexError> {
+        if config.match_mode == MatchMode::Standard {
+            let field = self.query_field(config)?;
+            let mut query_parser = QueryParser::for_index(&self.index, vec![field]);
+            if config.default_conjunction == Conjunction::And {
+                query_parser.set_conjunction_by_default();
+            }
+            if let (Some(slop), Some(phrase)) = (config.phrase_slop, quoted_phrase(query_str)) {
+                return self.phrase_query(field, phrase, slop);
+            }
+            return query_parser.parse_query(query_str).map_err(|e| match e {
+                QueryParserError::FieldDoesNotHavePositionsIndexed(_) => {
+                    IndexError::InvalidQuery(
+                        "case_sensitive and whole_word match single words, not phrases or \
+                         words joined by punctuation such as user_name"
+                            .to_string(),
+                    )
                 }
+                e => e.into(),
+            });
+        }
+        
+        // Tokenize like the indexed text so terms line up (lowercasing etc.)
+        let mut analyzer = self.index.tokenizer_for_field(self.text_field)?;
+        let mut stream = analyzer.token_stream(query_str);
+        let mut terms = Vec::new();
+        while let Some(token) = stream.next() {
+            terms.push(Term::from_field_text(self.text_field, &token.text));
+        }
+        if terms.is_empty() {
+            return Ok(Box::new(EmptyQuery));
+        }
+        
+        let occur = match config.default_conjunction {
+            Conjunction::Or => Occur::Should,
+            Conjunction::And => Occur::Must,
+        };
+        let clauses = terms
+            .into_iter()
+            .map(|term| {
+                let query: Box<dyn Query> = match config.match_mode {
+                    MatchMode::Prefix => Box::new(FuzzyTermQuery::new_prefix(term, 0, true)),
+                    _ => Box::new(FuzzyTermQuery::new(term, 1, true)),
+                };
+                (occur, query)
+            })
+            .collect();
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+    
+    /// The field unqualified terms search: `text`, or one of its whole-word copies
+    ///
+    /// Indexes created before the copies existed reject the options that need them.
+    fn query_field(&self, config: &SearchConfig) -> Result<Field, IndexError> {
+        let (field, name) = if config.case_sensitive {
+            (self.text_cased_field, "text_cased")
+        } else if config.whole_word {
+            (self.text_words_field, "text_words")
+        } else {
+            return Ok(self.text_field);
+        };
+        field.ok_or_else(|| QueryParserError::FieldDoesNotExist(name.to_string()).into())
+    }
+    
+    /// Match `phrase` with its terms at most `slop` positions out of place
+    fn phrase_query(
+        &self,
+        field: Field,
+        phrase: &str,
+        slop: u32,
+    ) -> Result<Box<dyn Query>, IndexError> {
+        let mut analyzer = self.index.tokenizer_for_field(field)?;
+        let mut stream = analyzer.token_stream(phrase);
+        let mut terms = Vec::new();
+        while let Some(token) = stream.next() {
+            // CamelCase parts share their identifier's position
+            terms.push((token.position, Term::from_field_text(field, &token.text)));
+        }
+        
+        let positions: BTreeSet<usize> = terms.iter().map(|(position, _)| *position).collect();
+        match positions.len() {
+            0 => Ok(Box::new(EmptyQuery)),
+            1 => {
+                let clauses = terms
+                    .into_iter()
+                    .map(|(_, term)| {
+                        let query: Box<dyn Query> =
+                            Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+                        (Occur::Must, query)
+                    })
+                    .collect();
+                Ok(Box::new(BooleanQuery::new(clauses)))
             }
+            _ => {
+                let mut query = PhraseQuery::new_with_offset(terms);
+                query.set_slop(slop);
+                Ok(Box::new(query))
+            }
+        }
+    }
+    
+    /// AND a query with an OR of term filters on the store field
+    fn restrict_to_stores(&self, query: Box<dyn Query>, stores: &[&str]) -> Box<dyn Query> {
+        let Some(store_field) = self.store_field else {
+            // Without a store field every document is implicitly in the default store
+            return if stores.contains(&DEFAULT_STORE) { query } else { Box::new(EmptyQuery) };
+        };
+        
+        let any_store = stores
+            .iter()
+            .map(|store| {
+                let term = Term::from_field_text(store_field, store);
+                let clause: Box<dyn Query> =
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+                (Occur::Should, clause)
+            })
+            .collect();
+        // The filter adds no score, or rarer stores would outrank common ones
+        let any_store = ConstScoreQuery::new(Box::new(BooleanQuery::new(any_store)), 0.0);
+        Box::new(BooleanQuery::new(vec![
+            (Occur::Must, query),
+            (Occur::Must, Box::new(any_store)),
+        ]))
+    }
+    
+    /// AND a query with an OR of term filters on the language field
+    fn restrict_to_languages(&self, query: Box<dyn Query>, languages: &[String]) -> Box<dyn Query> {
+        let Some(language_field) = self.language_field else {
+            // Indexes without a language field have no chunk in any language
+            return Box::new(EmptyQuery);
+        };
+        
+        let any_language = languages
+            .iter()
+            .map(|language| {
+                let term = Term::from_field_text(language_field, language);
+                let clause: Box<dyn Query> =
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+                (Occur::Should, clause)
+            })
+            .collect();
+        Box::new(BooleanQuery::new(vec![
+            (Occur::Must, query),
+            (Occur::Must, Box::new(BooleanQuery::new(any_language))),
+        ]))
+    }
+    
+    /// Look up the live document with an exact chunk id
+    fn find_by_chunk_id(
+        &self,
+        searcher: &Searcher,
+        chunk_id: &str,
+    ) -> Result<Option<TantivyDocument>, IndexError> {
+        let term = Term::from_field_text(self.chunk_id_field, chunk_id);
+        let query = TermQuery::new(term, IndexRecordOption::Basic);
+        match searcher.search(&query, &TopDocs::with_limit(1))?.pop() {
+            Some((_, doc_address)) => Ok(Some(searcher.doc(doc_address)?)),
+            None => Ok(None),
+        }
+    }
+    
+    fn read_stored_chunk(&self, doc: &TantivyDocument) -> StoredChunk {
+        let text = |field: Field| {
+            doc.get_first(field)
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        StoredChunk {
+            chunk_id: text(self.chunk_id_field),
+            text: text(self.text_field),
+            metadata: self.read_metadata(doc),
+            indexed_at: self.read_indexed_at(doc),
         }
+    }
+    
+    /// Read the indexing timestamp as RFC 3339, if the document has one
+    fn read_indexed_at(&self, doc: &TantivyDocument) -> Option<String> {
+        self.indexed_at_field
+            .and_then(|field| doc.get_first(field))
+            .and_then(|value| value.as_datetime())
+            .and_then(|date| date.into_utc().format(&Rfc3339).ok())
+    }
+    
+    /// Read stored metadata from a document, treating absent fields as empty/zero
+    fn read_metadata(&self, doc: &TantivyDocument) -> ChunkMetadata {
+        let text = |field: Option<Field>| {
+            field
+                .and_then(|f| doc.get_first(f))
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let number = |field: Option<Field>| {
+            field
+                .and_then(|f| doc.get_first(f))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0)
+        };
         
-        Ok(results)
+        let store = text(self.store_field);
+        
+        ChunkMetadata {
+            store: if store.is_empty() { DEFAULT_STORE.to_string() } else { store },
+            path: text(self.path_field),
+            language: text(self.language_field),
+            start_line: number(self.start_line_field),
+            end_line: number(self.end_line_field),
+        }
+    }
+}
+
+/// Require `min_match` of the top-level optional clauses of `query`
+///
+/// A query that is not a boolean query, e.g. a single term, counts as one clause.
+fn require_min_match(
+    query: Box<dyn Query>,
+    min_match: MinMatch,
+) -> Result<Box<dyn Query>, IndexError> {
+    let mut query = match query.downcast::<BooleanQuery>() {
+        Ok(query) => query,
+        Err(single) => {
+            min_match.required(1).map_err(IndexError::InvalidQuery)?;
+            return Ok(single);
+        }
+    };
+    let optional = query.clauses().iter().filter(|(occur, _)| *occur == Occur::Should).count();
+    let required = min_match.required(optional).map_err(IndexError::InvalidQuery)?;
+    query.set_minimum_number_should_match(required);
+    Ok(query)
+}
+
+/// The inside of a query that is exactly one `"quoted phrase"`
+fn quoted_phrase(query_str: &str) -> Option<&str> {
+    let phrase = query_str.trim().strip_prefix('"')?.strip_suffix('"')?;
+    (!phrase.contains('"')).then_some(phrase)
+}
+
+/// Fingerprint of everything `add_document` stores except the timestamp
+fn content_hash(text: &str, metadata: &ChunkMetadata) -> String {
+    let store = if metadata.store.is_empty() { DEFAULT_STORE } else { &metadata.store };
+    let mut hasher = Sha256::new();
+    // Length-prefixed so moving bytes between fields changes the hash
+    for part in [text, store, &metadata.path, &metadata.language] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
     }
+    hasher.update(metadata.start_line.to_le_bytes());
+    hasher.update(metadata.end_line.to_le_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Parse the `start-end` suffix of a chunk id, treating anything malformed as zero
+fn parse_line_range(range: &str) -> (u64, u64) {
+    let mut parts = range.splitn(2, '-');
+    let start = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let end = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    (start, end)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tantivy::schema::TEXT;
     use tempfile::TempDir;
     
+    fn paged(limit: usize, offset: usize) -> SearchConfig {
+        SearchConfig {
+            limit,
+            offset,
+            ..SearchConfig::default()
+        }
+    }
+    
     #[test]
     fn test_index_and_search() {
         let temp_dir = TempDir::new().unwrap();
-        let mut index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
         
         // Index some documents
-        index.add_document("chunk1", "hello world rust programming").unwrap();
-        index.add_document("chunk2", "python machine learning").unwrap();
-        index.add_document("chunk3", "rust systems programming").unwrap();
+        index
+            .add_document("chunk1", "hello world rust programming", &ChunkMetadata::default())
+            .unwrap();
+        index.add_document("chunk2", "python machine learning", &ChunkMetadata::default()).unwrap();
+        index
+            .add_document("chunk3", "rust systems programming", &ChunkMetadata::default())
+            .unwrap();
         index.commit().unwrap();
         
         // Search
-        let results = index.search("rust", 10).unwrap();
+        let results = index.search("rust", &SearchConfig::default()).unwrap().hits;
         assert_eq!(results.len(), 2);
         
         // First result should be about rust
-        assert!(results[0].0 == "chunk1" || results[0].0 == "chunk3");
+        assert!(results[0].chunk_id == "chunk1" || results[0].chunk_id == "chunk3");
+    }
+    
+    #[test]
+    fn test_metadata_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let metadata = ChunkMetadata {
+            path: "src/index.rs".to_string(),
+            language: "rust".to_string(),
+            start_line: 10,
+            end_line: 42,
+            ..ChunkMetadata::default()
+        };
+        index.add_document("src/index.rs#10-42", "fn add_document()", &metadata).unwrap();
+        index.commit().unwrap();
+        
+        let results = index.search("add_document", &SearchConfig::default()).unwrap().hits;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].metadata.path, "src/index.rs");
+        assert_eq!(results[0].metadata.language, "rust");
+        assert_eq!(results[0].metadata.start_line, 10);
+        assert_eq!(results[0].metadata.end_line, 42);
+    }
+    
+    #[test]
+    fn test_opens_index_without_metadata_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        
+        // Create an index with the original two-field schema
+        {
+            let mut schema_builder = Schema::builder();
+            let chunk_id = schema_builder.add_text_field("chunk_id", STRING | STORED);
+            let text = schema_builder.add_text_field("text", TEXT);
+            let dir = MmapDirectory::open(temp_dir.path()).unwrap();
+            let index =
+                Index::create(dir, schema_builder.build(), tantivy::IndexSettings::default())
+                    .unwrap();
+            let mut writer: IndexWriter = index.writer(15_000_000).unwrap();
+            let mut doc = TantivyDocument::default();
+            doc.add_text(chunk_id, "legacy");
+            doc.add_text(text, "legacy rust chunk");
+            writer.add_document(doc).unwrap();
+            writer.commit().unwrap();
+        }
+        
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let metadata = ChunkMetadata {
+            path: "ignored.rs".to_string(),
+            ..ChunkMetadata::default()
+        };
+        index.add_document("fresh", "fresh rust chunk", &metadata).unwrap();
+        index.commit().unwrap();
+        
+        // Absent fields read back as empty/zero
+        let results = index.search("rust", &SearchConfig::default()).unwrap().hits;
+        assert_eq!(results.len(), 2);
+        for hit in results {
+            assert_eq!(hit.metadata.path, "");
+            assert_eq!(hit.metadata.start_line, 0);
+        }
+        
+        // The whole-word copies of `text` are missing too
+        let case_sensitive = SearchConfig { case_sensitive: true, ..SearchConfig::default() };
+        assert!(matches!(
+            index.search("rust", &case_sensitive),
+            Err(IndexError::QueryParse(_))
+        ));
+    }
+    
+    #[test]
+    fn test_conjunction_mode_requires_all_terms() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        index.add_document("both", "async runtime scheduler", &ChunkMetadata::default()).unwrap();
+        index.add_document("async", "async fn main", &ChunkMetadata::default()).unwrap();
+        index.add_document("runtime", "runtime panics", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        // OR stays the default
+        let results = index.search("async runtime", &SearchConfig::default()).unwrap().hits;
+        assert_eq!(results.len(), 3);
+        
+        let config = SearchConfig {
+            default_conjunction: Conjunction::And,
+            ..SearchConfig::default()
+        };
+        let results = index.search("async runtime", &config).unwrap().hits;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk_id, "both");
+    }
+    
+    #[test]
+    fn test_search_across_stores_ranks_together() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let in_store = |store: &str| ChunkMetadata {
+            store: store.to_string(),
+            ..ChunkMetadata::default()
+        };
+        index.add_document("a1", "tokio tokio tokio", &in_store("acme")).unwrap();
+        index.add_document("g1", "tokio tokio runtime", &in_store("globex")).unwrap();
+        index.add_document("a2", "tokio runtime executor scheduler", &in_store("acme")).unwrap();
+        index.add_document("p1", "tokio tokio tokio tokio", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        let config = SearchConfig {
+            stores: vec!["acme".to_string(), "globex".to_string()],
+            ..SearchConfig::default()
+        };
+        let results = index.search("tokio", &config).unwrap().hits;
+        let ranked: Vec<_> = results
+            .iter()
+            .map(|hit| (hit.chunk_id.as_str(), hit.metadata.store.as_str()))
+            .collect();
+        assert_eq!(ranked, vec![("a1", "acme"), ("g1", "globex"), ("a2", "acme")]);
+        assert!(results.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(index.count("tokio", &config).unwrap(), 3);
+    }
+    
+    #[test]
+    fn test_phrase_slop_allows_words_between_terms() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        index
+            .add_document("near", "spawn a background task", &ChunkMetadata::default())
+            .unwrap();
+        index.add_document("exact", "spawn task now", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        let with_slop = |slop: u32| SearchConfig {
+            phrase_slop: Some(slop),
+            ..SearchConfig::default()
+        };
+        let ids = |config: &SearchConfig| -> BTreeSet<String> {
+            let results = index.search("\"spawn task\"", config).unwrap().hits;
+            results.into_iter().map(|hit| hit.chunk_id).collect()
+        };
+        
+        assert_eq!(ids(&with_slop(2)), BTreeSet::from(["near".into(), "exact".into()]));
+        assert_eq!(ids(&with_slop(0)), BTreeSet::from(["exact".into()]));
+        assert_eq!(quoted_phrase("spawn task"), None);
+    }
+    
+    #[test]
+    fn test_search_filtered_by_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let in_store = |store: &str| ChunkMetadata {
+            store: store.to_string(),
+            ..ChunkMetadata::default()
+        };
+        index.add_document("a", "tokio runtime", &in_store("acme")).unwrap();
+        index.add_document("b", "tokio runtime", &in_store("globex")).unwrap();
+        index.add_document("c", "tokio runtime", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        // No store searches everything
+        assert_eq!(index.search("tokio", &SearchConfig::default()).unwrap().hits.len(), 3);
+        
+        let config = SearchConfig {
+            store: Some("acme".to_string()),
+            ..SearchConfig::default()
+        };
+        let results = index.search("tokio", &config).unwrap().hits;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk_id, "a");
+        assert_eq!(results[0].metadata.store, "acme");
+        
+        let counts = index.store_doc_counts().unwrap();
+        assert_eq!(counts.get("acme"), Some(&1));
+        assert_eq!(counts.get("globex"), Some(&1));
+        assert_eq!(counts.get(DEFAULT_STORE), Some(&1));
+    }
+    
+    fn match_mode_fixture() -> (TempDir, TantivyIndex) {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        index.add_document("tantivy", "tantivy search engine", &ChunkMetadata::default()).unwrap();
+        index.add_document("tokio", "tokio async runtime", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        (temp_dir, index)
+    }
+    
+    fn with_mode(match_mode: MatchMode) -> SearchConfig {
+        SearchConfig {
+            match_mode,
+            ..SearchConfig::default()
+        }
+    }
+    
+    #[test]
+    fn test_standard_mode_matches_whole_terms() {
+        let (_dir, index) = match_mode_fixture();
+        
+        assert!(index.search("tant", &with_mode(MatchMode::Standard)).unwrap().hits.is_empty());
+        assert_eq!(index.search("tantivy", &with_mode(MatchMode::Standard)).unwrap().hits.len(), 1);
+    }
+    
+    #[test]
+    fn test_prefix_mode_matches_term_prefixes() {
+        let (_dir, index) = match_mode_fixture();
+        
+        let results = index.search("Tant", &with_mode(MatchMode::Prefix)).unwrap().hits;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk_id, "tantivy");
+        
+        // A typo is not a prefix
+        assert!(index.search("tantvy", &with_mode(MatchMode::Prefix)).unwrap().hits.is_empty());
+    }
+    
+    #[test]
+    fn test_fuzzy_mode_tolerates_one_edit() {
+        let (_dir, index) = match_mode_fixture();
+        
+        let results = index.search("tokoi", &with_mode(MatchMode::Fuzzy)).unwrap().hits;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk_id, "tokio");
+        
+        // Two edits away is too far
+        assert!(index.search("tkoi", &with_mode(MatchMode::Fuzzy)).unwrap().hits.is_empty());
+    }
+    
+    #[test]
+    fn test_case_sensitive_and_whole_word() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_str().unwrap();
+        let index =
+            TantivyIndex::with_options(path, WriterConfig::default(), TextTokenizer::Code).unwrap();
+        index.add_document("type", "struct User", &ChunkMetadata::default()).unwrap();
+        index.add_document("var", "let user = 1", &ChunkMetadata::default()).unwrap();
+        index.add_document("fn", "fn getUserName()", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        let ids = |query: &str, config: SearchConfig| -> BTreeSet<String> {
+            let results = index.search(query, &config).unwrap().hits;
+            results.into_iter().map(|hit| hit.chunk_id).collect()
+        };
+        let set = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<BTreeSet<_>>();
+        let case_sensitive = || SearchConfig { case_sensitive: true, ..SearchConfig::default() };
+        let whole_word = || SearchConfig { whole_word: true, ..SearchConfig::default() };
+        
+        // By default case is ignored and, with `code`, identifier parts match
+        assert_eq!(ids("User", SearchConfig::default()), set(&["type", "var", "fn"]));
+        assert_eq!(ids("User", case_sensitive()), set(&["type"]));
+        assert_eq!(ids("user", case_sensitive()), set(&["var"]));
+        assert_eq!(ids("user", whole_word()), set(&["type", "var"]));
+        assert_eq!(ids("getusername", whole_word()), set(&["fn"]));
+        
+        let fuzzy = SearchConfig { match_mode: MatchMode::Fuzzy, ..case_sensitive() };
+        assert!(fuzzy.validate().is_err());
+        let slop = SearchConfig { phrase_slop: Some(1), ..whole_word() };
+        assert!(slop.validate().is_err());
+        
+        // The copies have no positions, so phrases are a clear error rather than a crash
+        let schema = index.index.schema();
+        for name in ["text_words", "text_cased"] {
+            let entry = schema.get_field_entry(schema.get_field(name).unwrap());
+            let indexing = entry.field_type().get_index_record_option().unwrap();
+            assert!(!indexing.has_positions(), "{} has positions", name);
+        }
+        for query in ["\"struct User\"", "user_name"] {
+            let result = index.search(query, &whole_word());
+            assert!(matches!(result, Err(IndexError::InvalidQuery(_))), "query {:?}", query);
+        }
+    }
+    
+    #[test]
+    fn test_min_match_requires_enough_terms() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let meta = ChunkMetadata::default();
+        index.add_document("all", "async fn main tokio spawn runtime", &meta).unwrap();
+        index.add_document("three", "async tokio spawn", &meta).unwrap();
+        index.add_document("two", "async runtime", &meta).unwrap();
+        index.add_document("one", "tokio", &meta).unwrap();
+        index.commit().unwrap();
+        
+        let ids = |min_match: MinMatch| -> Result<BTreeSet<String>, IndexError> {
+            let config = SearchConfig { min_match: Some(min_match), ..SearchConfig::default() };
+            let results = index.search("async tokio spawn runtime", &config)?.hits;
+            Ok(results.into_iter().map(|hit| hit.chunk_id).collect())
+        };
+        let set = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<BTreeSet<_>>();
+        
+        assert_eq!(ids(MinMatch::Count(3)).unwrap(), set(&["all", "three"]));
+        assert_eq!(ids(MinMatch::Percent(50)).unwrap(), set(&["all", "three", "two"]));
+        assert_eq!(ids(MinMatch::Count(1)).unwrap().len(), 4);
+        assert!(matches!(ids(MinMatch::Count(5)), Err(IndexError::InvalidQuery(_))));
+        
+        // Counting matches agrees with the page
+        let config =
+            SearchConfig { min_match: Some(MinMatch::Count(3)), ..SearchConfig::default() };
+        assert_eq!(index.count("async tokio spawn runtime", &config).unwrap(), 2);
+    }
+    
+    #[test]
+    fn test_get_document() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let metadata = ChunkMetadata {
+            path: "src/lib.rs".to_string(),
+            language: "rust".to_string(),
+            start_line: 1,
+            end_line: 3,
+            ..ChunkMetadata::default()
+        };
+        index.add_document("src/lib.rs#1-3", "pub mod index;", &metadata).unwrap();
+        index.commit().unwrap();
+        
+        let doc = index.get_document("src/lib.rs#1-3").unwrap().unwrap();
+        assert_eq!(doc.text, "pub mod index;");
+        assert_eq!(doc.metadata.path, "src/lib.rs");
+        assert_eq!(doc.metadata.end_line, 3);
+        assert!(doc.indexed_at.is_some());
+        
+        assert!(index.get_document("src/lib.rs#4-6").unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_search_with_offset() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        index.add_document("chunk1", "rust rust rust", &ChunkMetadata::default()).unwrap();
+        index.add_document("chunk2", "rust rust", &ChunkMetadata::default()).unwrap();
+        index.add_document("chunk3", "rust", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        let all = index.search("rust", &SearchConfig::default()).unwrap().hits;
+        assert_eq!(all.len(), 3);
+        
+        // Paging through with offset yields the same ordering as a single fetch
+        let first = index.search("rust", &paged(2, 0)).unwrap().hits;
+        let second = index.search("rust", &paged(2, 2)).unwrap().hits;
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 1);
+        assert_eq!(first[0].chunk_id, all[0].chunk_id);
+        assert_eq!(first[1].chunk_id, all[1].chunk_id);
+        assert_eq!(second[0].chunk_id, all[2].chunk_id);
+        
+        // Offset past the end returns nothing
+        assert!(index.search("rust", &paged(2, 10)).unwrap().hits.is_empty());
+    }
+    
+    #[test]
+    fn test_code_tokenizer_matches_identifier_parts() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("code");
+        let index = TantivyIndex::with_options(
+            path.to_str().unwrap(),
+            WriterConfig::default(),
+            TextTokenizer::Code,
+        )
+        .unwrap();
+        assert_eq!(index.tokenizer_name(), "code");
+        
+        index.add_document("a.ts#1-3", "function getUserName() {}", &ChunkMetadata::default())
+            .unwrap();
+        index.commit().unwrap();
+        
+        for query in ["user", "name", "getUserName", "getusername"] {
+            let hits = index.search(query, &SearchConfig::default()).unwrap().hits;
+            assert_eq!(hits.len(), 1, "query {:?}", query);
+        }
+        
+        // New indexes use `code` unless told otherwise
+        let path = temp_dir.path().join("implicit");
+        assert_eq!(TantivyIndex::new(path.to_str().unwrap()).unwrap().tokenizer_name(), "code");
+        
+        // Tantivy's plain analyzer keeps identifiers whole
+        let path = temp_dir.path().join("default");
+        let index = TantivyIndex::with_options(
+            path.to_str().unwrap(),
+            WriterConfig::default(),
+            TextTokenizer::Default,
+        )
+        .unwrap();
+        assert_eq!(index.tokenizer_name(), "default");
+        index.add_document("a.ts#1-3", "function getUserName() {}", &ChunkMetadata::default())
+            .unwrap();
+        index.commit().unwrap();
+        assert!(index.search("user", &SearchConfig::default()).unwrap().hits.is_empty());
+        assert_eq!(index.search("getusername", &SearchConfig::default()).unwrap().hits.len(), 1);
+    }
+    
+    #[test]
+    fn test_code_stem_tokenizer_matches_word_forms() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_str().unwrap();
+        let index =
+            TantivyIndex::with_options(path, WriterConfig::default(), TextTokenizer::CodeStem)
+                .unwrap();
+        index.add_document("doc#1-1", "Running the parsers", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        drop(index);
+        
+        // Reopening keeps the stored tokenizer whatever is requested now
+        let index =
+            TantivyIndex::with_options(path, WriterConfig::default(), TextTokenizer::Default)
+                .unwrap();
+        assert_eq!(index.tokenizer_name(), "code_stem");
+        assert_eq!(index.search("run parser", &SearchConfig::default()).unwrap().hits.len(), 1);
+    }
+    
+    #[test]
+    fn test_merge_collapses_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        for i in 0..3 {
+            let id = format!("chunk{}", i);
+            index.add_document(&id, "merge me", &ChunkMetadata::default()).unwrap();
+            index.commit().unwrap();
+        }
+        assert_eq!(index.segment_count(), 3);
+        
+        assert!(index.optimize().unwrap());
+        assert_eq!(index.segment_count(), 1);
+        assert_eq!(index.search("merge", &SearchConfig::default()).unwrap().hits.len(), 3);
+        
+        // A single segment has nothing left to merge
+        assert!(!index.optimize().unwrap());
+    }
+    
+    #[test]
+    fn test_merge_purges_deleted_docs_from_single_segment() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        for i in 0..3 {
+            let id = format!("chunk{}", i);
+            index.add_document(&id, "vacuum me", &ChunkMetadata::default()).unwrap();
+        }
+        index.commit().unwrap();
+        index.delete_document("chunk0").unwrap();
+        index.commit().unwrap();
+        assert_eq!(index.segment_count(), 1);
+        assert_eq!(index.deleted_doc_count(), 1);
+        
+        assert!(index.optimize().unwrap());
+        assert_eq!(index.deleted_doc_count(), 0);
+        assert_eq!(index.doc_count(), 2);
+    }
+    
+    #[test]
+    fn test_search_does_not_wait_for_writer() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        index.add_document("chunk1", "reader stays free", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        let hold = std::time::Duration::from_millis(500);
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let _writer = index.writer();
+                locked_tx.send(()).unwrap();
+                std::thread::sleep(hold);
+            });
+            locked_rx.recv().unwrap();
+            
+            // Runs while another thread holds the writer
+            let started = std::time::Instant::now();
+            assert_eq!(index.search("reader", &SearchConfig::default()).unwrap().hits.len(), 1);
+            assert!(started.elapsed() < hold);
+        });
+    }
+    
+    #[test]
+    fn test_highlight_reports_first_matching_line() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let metadata = ChunkMetadata {
+            path: "src/user.rs".to_string(),
+            start_line: 10,
+            end_line: 14,
+            ..ChunkMetadata::default()
+        };
+        let text = "use std::fmt;\n\npub struct Account;\n\nfn getUserName() {}";
+        index.add_document("src/user.rs#10-14", text, &metadata).unwrap();
+        index.commit().unwrap();
+        
+        let search = |query: &str, match_mode: MatchMode| {
+            let config = SearchConfig {
+                highlight: true,
+                match_mode,
+                ..SearchConfig::default()
+            };
+            index.search(query, &config).unwrap().hits[0].match_line
+        };
+        assert_eq!(search("account", MatchMode::Standard), Some(12));
+        assert_eq!(search("name OR fmt", MatchMode::Standard), Some(10));
+        assert_eq!(search("getuser", MatchMode::Prefix), Some(14));
+        
+        // Off unless requested
+        let plain = index.search("account", &SearchConfig::default()).unwrap().hits;
+        assert_eq!(plain[0].match_line, None);
+    }
+    
+    #[test]
+    fn test_count_ignores_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        for i in 0..5 {
+            let id = format!("chunk{}", i);
+            index.add_document(&id, "rust crate", &ChunkMetadata::default()).unwrap();
+        }
+        index.add_document("other", "python module", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        let config = SearchConfig {
+            limit: 2,
+            ..SearchConfig::default()
+        };
+        assert_eq!(index.search("rust", &config).unwrap().hits.len(), 2);
+        assert_eq!(index.count("rust", &config).unwrap(), 5);
+        assert_eq!(index.count("missing", &config).unwrap(), 0);
+    }
+    
+    #[test]
+    fn test_search_counts_all_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        for i in 0..5 {
+            let id = format!("chunk{}", i);
+            index.add_document(&id, "rust crate", &ChunkMetadata::default()).unwrap();
+        }
+        index.commit().unwrap();
+        
+        let config = SearchConfig {
+            limit: 2,
+            offset: 1,
+            ..SearchConfig::default()
+        };
+        let page = index.search("rust", &config).unwrap();
+        assert_eq!(page.hits.len(), 2);
+        assert_eq!(page.total_hits, 5);
+        
+        // The rescoring collector counts in the same pass too
+        let rescored = SearchConfig {
+            bm25_b: 0.5,
+            ..config
+        };
+        let page = index.search("rust", &rescored).unwrap();
+        assert_eq!(page.hits.len(), 2);
+        assert_eq!(page.total_hits, 5);
+    }
+    
+    #[test]
+    fn test_chunks_for_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        index.add_document("src/main.rs#41-80", "fn run() {}", &ChunkMetadata::default()).unwrap();
+        index.add_document("src/main.rs#1-40", "fn main() {}", &ChunkMetadata::default()).unwrap();
+        index
+            .add_document("src/main.rs.bak#1-40", "fn main() {}", &ChunkMetadata::default())
+            .unwrap();
+        index.add_document("src/lib.rs#1-10", "pub mod index;", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        let chunks = index.chunks_for_path("src/main.rs").unwrap();
+        let ids: Vec<&str> = chunks.iter().map(|c| c.chunk_id.as_str()).collect();
+        assert_eq!(ids, vec!["src/main.rs#1-40", "src/main.rs#41-80"]);
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (41, 80));
+        assert!(chunks[0].indexed_at.is_some());
+        
+        // Unknown files report no chunks
+        assert!(index.chunks_for_path("src/missing.rs").unwrap().is_empty());
+        
+        // Deleted chunks are no longer listed
+        index.delete_document("src/main.rs#41-80").unwrap();
+        index.commit().unwrap();
+        assert_eq!(index.chunks_for_path("src/main.rs").unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_chunks_for_path_with_uuid_chunk_ids() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        // The backend ids chunks by UUID and sends the file as metadata
+        let chunk = |path: &str, start_line: u64, end_line: u64| ChunkMetadata {
+            path: path.to_string(),
+            start_line,
+            end_line,
+            ..ChunkMetadata::default()
+        };
+        let second = "0f8fad5b-d9cb-469f-a165-70867728950e";
+        let first = "7c9e6679-7425-40de-944b-e07fc1f90ae7";
+        let other = "a3bb189e-8bf9-3888-9912-ace4e6543002";
+        index.add_document(second, "fn run() {}", &chunk("src/main.rs", 41, 80)).unwrap();
+        index.add_document(first, "fn main() {}", &chunk("src/main.rs", 1, 40)).unwrap();
+        index.add_document(other, "fn main() {}", &chunk("src/main.rs.bak", 1, 40)).unwrap();
+        index.commit().unwrap();
+        
+        let chunks = index.chunks_for_path("src/main.rs").unwrap();
+        let ids: Vec<&str> = chunks.iter().map(|c| c.chunk_id.as_str()).collect();
+        assert_eq!(ids, vec![first, second]);
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (41, 80));
+        
+        index.delete_document(second).unwrap();
+        index.commit().unwrap();
+        assert_eq!(index.chunks_for_path("src/main.rs").unwrap().len(), 1);
+        assert!(index.chunks_for_path("src/lib.rs").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_indexed_paths_by_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let in_store = |store: &str| ChunkMetadata {
+            store: store.to_string(),
+            ..ChunkMetadata::default()
+        };
+        
+        index.add_document("/repo/a.rs#1-10", "a", &in_store("acme")).unwrap();
+        index.add_document("/repo/a.rs#11-20", "a", &in_store("acme")).unwrap();
+        index.add_document("/repo/b#c.rs#1-5", "b", &in_store("acme")).unwrap();
+        index.add_document("/repo/gone.rs#1-5", "gone", &in_store("acme")).unwrap();
+        index.add_document("/other/d.rs#1-5", "d", &in_store("public")).unwrap();
+        index.commit().unwrap();
+        index.delete_document("/repo/gone.rs#1-5").unwrap();
+        index.commit().unwrap();
+        
+        assert_eq!(
+            index.indexed_paths(Some("acme")).unwrap(),
+            vec!["/repo/a.rs", "/repo/b#c.rs"]
+        );
+        assert_eq!(index.indexed_paths(Some("public")).unwrap(), vec!["/other/d.rs"]);
+        assert_eq!(index.indexed_paths(None).unwrap().len(), 3);
+    }
+    
+    #[test]
+    fn test_indexed_paths_from_path_field() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let chunk = |path: &str, store: &str| ChunkMetadata {
+            path: path.to_string(),
+            store: store.to_string(),
+            ..ChunkMetadata::default()
+        };
+        
+        // UUID ids say nothing about the file; the stored path does
+        let a = "0f8fad5b-d9cb-469f-a165-70867728950e";
+        let b = "7c9e6679-7425-40de-944b-e07fc1f90ae7";
+        let c = "a3bb189e-8bf9-3888-9912-ace4e6543002";
+        index.add_document(a, "a", &chunk("/repo/a.rs", "acme")).unwrap();
+        index.add_document(b, "b", &chunk("/repo/b#1.rs", "acme")).unwrap();
+        index.add_document(c, "c", &chunk("/other/c.rs", "public")).unwrap();
+        // A path-style id is not split when the path is stored
+        index.add_document("/repo/d.rs#1-5", "d", &chunk("/repo/renamed.rs", "acme")).unwrap();
+        // Without a path or a path-style id there's nothing to list
+        index.add_document("e1", "e", &chunk("", "acme")).unwrap();
+        index.commit().unwrap();
+        
+        assert_eq!(
+            index.indexed_paths(Some("acme")).unwrap(),
+            vec!["/repo/a.rs", "/repo/b#1.rs", "/repo/renamed.rs"]
+        );
+        assert_eq!(index.indexed_paths(Some("public")).unwrap(), vec!["/other/c.rs"]);
+        
+        index.delete_document(a).unwrap();
+        index.commit().unwrap();
+        assert_eq!(
+            index.indexed_paths(Some("acme")).unwrap(),
+            vec!["/repo/b#1.rs", "/repo/renamed.rs"]
+        );
+    }
+    
+    #[test]
+    fn test_unchanged_content_is_not_rewritten() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let metadata = ChunkMetadata {
+            path: "src/lib.rs".to_string(),
+            ..ChunkMetadata::default()
+        };
+        
+        assert!(index.add_document("src/lib.rs#1-5", "fn one() {}", &metadata).unwrap());
+        index.commit().unwrap();
+        
+        assert!(!index.add_document("src/lib.rs#1-5", "fn one() {}", &metadata).unwrap());
+        assert_eq!(index.pending_ops(), 0);
+        
+        // Different metadata is a change even with the same text
+        let moved = ChunkMetadata {
+            path: "src/main.rs".to_string(),
+            ..ChunkMetadata::default()
+        };
+        assert!(index.add_document("src/lib.rs#1-5", "fn one() {}", &moved).unwrap());
+        
+        // Once staged, the committed copy no longer says what the chunk will be
+        assert!(index.add_document("src/lib.rs#1-5", "fn one() {}", &metadata).unwrap());
+        index.commit().unwrap();
+        assert_eq!(index.doc_count(), 1);
+        let stored = index.get_document("src/lib.rs#1-5").unwrap().unwrap();
+        assert_eq!(stored.metadata.path, "src/lib.rs");
+    }
+    
+    #[test]
+    fn test_staged_changes_invisible_until_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        index.add_document("chunk1", "hello world", &ChunkMetadata::default()).unwrap();
+        assert_eq!(index.pending_ops(), 1);
+        assert_eq!(index.doc_count(), 0);
+        assert!(index.search("hello", &SearchConfig::default()).unwrap().hits.is_empty());
+        
+        index.commit().unwrap();
+        assert_eq!(index.pending_ops(), 0);
+        assert_eq!(index.doc_count(), 1);
+    }
+    
+    #[test]
+    fn test_delete_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        index.add_document("src/a.rs#1-10", "alpha", &ChunkMetadata::default()).unwrap();
+        index.add_document("src/a.rs#11-20", "alpha", &ChunkMetadata::default()).unwrap();
+        index.add_document("src/b.rs#1-10", "alpha", &ChunkMetadata::default()).unwrap();
+        
+        // Staged (uncommitted) chunks are still found
+        assert_eq!(index.delete_prefix("src/a.rs#", None).unwrap(), 2);
+        index.commit().unwrap();
+        
+        assert_eq!(index.doc_count(), 1);
+        assert_eq!(index.chunk_ids_with_prefix("src/").unwrap(), vec!["src/b.rs#1-10"]);
+    }
+    
+    #[test]
+    fn test_delete_prefix_scoped_to_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let acme = ChunkMetadata { store: "acme".to_string(), ..ChunkMetadata::default() };
+        index.add_document("src/a.rs#1-10", "alpha", &acme).unwrap();
+        index.add_document("src/a.rs#11-20", "alpha", &ChunkMetadata::default()).unwrap();
+        
+        assert_eq!(index.delete_prefix("src/a.rs#", Some("acme")).unwrap(), 1);
+        index.commit().unwrap();
+        
+        assert_eq!(index.chunk_ids_with_prefix("src/").unwrap(), vec!["src/a.rs#11-20"]);
+    }
+    
+    #[test]
+    fn test_clear_store_leaves_other_stores() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let acme = ChunkMetadata { store: "acme".to_string(), ..ChunkMetadata::default() };
+        index.add_document("a.rs#1-10", "alpha", &acme).unwrap();
+        index.add_document("b.rs#1-10", "alpha", &acme).unwrap();
+        index.add_document("c.rs#1-10", "alpha", &ChunkMetadata::default()).unwrap();
+        
+        assert_eq!(index.clear_store("acme").unwrap(), 2);
+        assert_eq!(index.doc_count(), 1);
+        assert_eq!(index.chunk_ids_with_prefix("").unwrap(), vec!["c.rs#1-10"]);
+    }
+    
+    #[test]
+    fn test_bm25_b_changes_length_normalization() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let filler = "lorem ipsum dolor sit amet ".repeat(4);
+        index.add_document("short", "parser", &ChunkMetadata::default()).unwrap();
+        index
+            .add_document("long", &format!("parser parser {}", filler), &ChunkMetadata::default())
+            .unwrap();
+        index.commit().unwrap();
+        
+        let ranking = |config: &SearchConfig| -> Vec<String> {
+            let hits = index.search("parser", config).unwrap().hits;
+            hits.into_iter().map(|hit| hit.chunk_id).collect()
+        };
+        
+        // Default b = 0.75 favors the short chunk
+        assert_eq!(ranking(&SearchConfig::default()), vec!["short", "long"]);
+        
+        // Without length normalization the extra occurrence wins
+        let no_length_norm = SearchConfig { bm25_b: 0.0, ..Default::default() };
+        assert_eq!(ranking(&no_length_norm), vec!["long", "short"]);
+    }
+    
+    #[test]
+    fn test_custom_bm25_with_default_values_matches_builtin_scores() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        index.add_document("a", "async runtime tokio", &ChunkMetadata::default()).unwrap();
+        index.add_document("b", "tokio tokio executor", &ChunkMetadata::default()).unwrap();
+        index.add_document("c", "unrelated text", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        
+        // A k1 a hair off the default goes through the rescoring path
+        let builtin = index.search("tokio runtime", &SearchConfig::default()).unwrap().hits;
+        let rescored = SearchConfig { bm25_k1: 1.2 + f32::EPSILON * 4.0, ..Default::default() };
+        let rescored = index.search("tokio runtime", &rescored).unwrap().hits;
+        
+        assert_eq!(builtin.len(), rescored.len());
+        for (expected, actual) in builtin.iter().zip(&rescored) {
+            assert_eq!(expected.chunk_id, actual.chunk_id);
+            assert!((expected.score - actual.score).abs() < 1e-3);
+        }
+    }
+    
+    #[test]
+    fn test_custom_bm25_b_ranks_phrases_and_qualified_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let rust = ChunkMetadata { language: "rust".to_string(), ..ChunkMetadata::default() };
+        let python = ChunkMetadata { language: "python".to_string(), ..ChunkMetadata::default() };
+        let filler = "lorem ipsum dolor sit amet ".repeat(4);
+        index.add_document("short", "parse tree", &rust).unwrap();
+        index.add_document("long", &format!("parse tree parse tree {}", filler), &rust).unwrap();
+        index.add_document("reversed", "tree parse", &rust).unwrap();
+        index.add_document("script", "unrelated", &python).unwrap();
+        index.commit().unwrap();
+        
+        let search = |query: &str, b: f32| -> Vec<(String, f32)> {
+            let config = SearchConfig { bm25_b: b, ..Default::default() };
+            let hits = index.search(query, &config).unwrap().hits;
+            hits.into_iter().map(|hit| (hit.chunk_id, hit.score)).collect()
+        };
+        let ids = |hits: Vec<(String, f32)>| -> Vec<String> {
+            hits.into_iter().map(|(id, _)| id).collect()
+        };
+        
+        // A phrase still only matches in order, with b deciding between lengths
+        assert_eq!(ids(search("\"parse tree\"", 1.0)), vec!["short", "long"]);
+        assert_eq!(ids(search("\"parse tree\"", 0.0)), vec!["long", "short"]);
+        
+        // Qualified terms keep scoring next to the text
+        let hits = search("parse AND language:rust", 0.0);
+        assert_eq!(ids(hits.clone())[0], "long");
+        let hits = search("parse OR language:python", 0.0);
+        assert_eq!(hits.len(), 4);
+        assert!(hits.iter().all(|(_, score)| *score > 0.0));
+        
+        // With the default constants they score as Tantivy does
+        let near_default = SearchConfig { bm25_k1: 1.2 + f32::EPSILON * 4.0, ..Default::default() };
+        let query = "tree language:python";
+        let builtin = index.search(query, &SearchConfig::default()).unwrap().hits;
+        let rescored = index.search(query, &near_default).unwrap().hits;
+        assert_eq!(builtin.len(), rescored.len());
+        for (expected, actual) in builtin.iter().zip(&rescored) {
+            assert_eq!(expected.chunk_id, actual.chunk_id);
+            assert!((expected.score - actual.score).abs() < 1e-3);
+        }
+    }
+    
+    #[test]
+    fn test_field_qualified_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let chunks = [
+            ("src/lib.rs#1-20", "src/lib.rs", "rust", 1),
+            ("src/lib.rs#21-40", "src/lib.rs", "rust", 21),
+            ("app.py#1-20", "app.py", "python", 1),
+        ];
+        for (chunk_id, path, language, start_line) in chunks {
+            let metadata = ChunkMetadata {
+                path: path.to_string(),
+                language: language.to_string(),
+                start_line,
+                end_line: start_line + 19,
+                ..ChunkMetadata::default()
+            };
+            index.add_document(chunk_id, "async handler", &metadata).unwrap();
+        }
+        index.commit().unwrap();
+        
+        let and = SearchConfig {
+            default_conjunction: Conjunction::And,
+            ..SearchConfig::default()
+        };
+        let ids = |query: &str| {
+            let mut ids: Vec<_> = index
+                .search(query, &and)
+                .unwrap().hits
+                .into_iter()
+                .map(|hit| hit.chunk_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        
+        assert_eq!(ids("language:python async"), vec!["app.py#1-20"]);
+        assert_eq!(ids("text:async language:python"), vec!["app.py#1-20"]);
+        assert_eq!(ids(r#"path:"src/lib.rs" handler"#).len(), 2);
+        assert_eq!(ids("language:rust start_line:[10 TO 30]"), vec!["src/lib.rs#21-40"]);
+        // Unqualified terms still only search the text
+        assert!(ids("rust").is_empty());
+        assert_eq!(ids("async").len(), 3);
+    }
+    
+    #[test]
+    fn test_search_filters_by_any_of_several_languages() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let chunks = [("lib.rs#1-5", "rust"), ("Cargo.toml#1-5", "toml"), ("app.py#1-5", "python")];
+        for (chunk_id, language) in chunks {
+            let metadata = ChunkMetadata {
+                language: language.to_string(),
+                ..ChunkMetadata::default()
+            };
+            index.add_document(chunk_id, "serde config", &metadata).unwrap();
+        }
+        index.commit().unwrap();
+        
+        let languages = |languages: &[&str]| {
+            let config = SearchConfig {
+                languages: languages.iter().map(|l| l.to_string()).collect(),
+                ..Default::default()
+            };
+            let mut ids: Vec<_> = index
+                .search("serde", &config)
+                .unwrap().hits
+                .into_iter()
+                .map(|hit| hit.chunk_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        
+        assert_eq!(languages(&["rust", "toml"]), vec!["Cargo.toml#1-5", "lib.rs#1-5"]);
+        assert_eq!(languages(&["python"]), vec!["app.py#1-5"]);
+        assert_eq!(languages(&[]).len(), 3);
+    }
+    
+    #[test]
+    fn test_dedup_by_path_keeps_best_chunk_per_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        let texts = [
+            ("src/a.rs#1-10", "parser parser parser"),
+            ("src/a.rs#11-20", "parser parser"),
+            ("src/a.rs#21-30", "parser and friends"),
+            ("src/b.rs#1-10", "parser and other friends"),
+        ];
+        for (chunk_id, text) in texts {
+            let path = chunk_id.split('#').next().unwrap().to_string();
+            let metadata = ChunkMetadata { path, ..ChunkMetadata::default() };
+            index.add_document(chunk_id, text, &metadata).unwrap();
+        }
+        index.commit().unwrap();
+        
+        let config = SearchConfig { dedup_by_path: true, limit: 5, ..Default::default() };
+        let ids: Vec<_> = index
+            .search("parser", &config)
+            .unwrap().hits
+            .into_iter()
+            .map(|hit| hit.chunk_id)
+            .collect();
+        assert_eq!(ids, vec!["src/a.rs#1-10", "src/b.rs#1-10"]);
+        
+        // Pagination applies after collapsing
+        let second_page = SearchConfig { offset: 1, limit: 1, ..config };
+        let hits = index.search("parser", &second_page).unwrap().hits;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].chunk_id, "src/b.rs#1-10");
+    }
+    
+    #[test]
+    fn test_dedup_overfetch_fills_the_page() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        // Four strong chunks in one file outrank one chunk in each of four others
+        for i in 0..4 {
+            let metadata = ChunkMetadata { path: "big.rs".to_string(), ..Default::default() };
+            let id = format!("big.rs#{}", i);
+            index.add_document(&id, "cache cache cache", &metadata).unwrap();
+        }
+        for i in 0..4 {
+            let path = format!("small{}.rs", i);
+            let metadata = ChunkMetadata { path: path.clone(), ..Default::default() };
+            index.add_document(&format!("{}#1", path), "cache", &metadata).unwrap();
+        }
+        index.commit().unwrap();
+        
+        let hits = |overfetch: usize| {
+            let config = SearchConfig {
+                dedup_by_path: true,
+                limit: 3,
+                overfetch,
+                ..Default::default()
+            };
+            index.search("cache", &config).unwrap().hits.len()
+        };
+        assert_eq!(hits(1), 1);
+        assert_eq!(hits(5), 3);
+    }
+    
+    #[test]
+    fn test_writer_config_validation() {
+        assert!(WriterConfig::default().validate().is_ok());
+        
+        let too_small = WriterConfig { heap_mb: 10, num_threads: None };
+        assert!(matches!(too_small.validate(), Err(IndexError::Config(_))));
+        
+        // 50MB split over 4 threads leaves 12.5MB each
+        let too_thin = WriterConfig { heap_mb: 50, num_threads: Some(4) };
+        assert!(matches!(too_thin.validate(), Err(IndexError::Config(_))));
+        
+        let no_threads = WriterConfig { heap_mb: 50, num_threads: Some(0) };
+        assert!(matches!(no_threads.validate(), Err(IndexError::Config(_))));
+        
+        let too_large = WriterConfig { heap_mb: 5_000, num_threads: Some(1) };
+        assert!(matches!(too_large.validate(), Err(IndexError::Config(_))));
+        
+        let temp_dir = TempDir::new().unwrap();
+        let config = WriterConfig { heap_mb: 30, num_threads: Some(2) };
+        let path = temp_dir.path().to_str().unwrap();
+        let index =
+            TantivyIndex::with_options(path, config, TextTokenizer::default()).unwrap();
+        index.add_document("chunk1", "hello", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        assert_eq!(index.doc_count(), 1);
+    }
+    
+    #[test]
+    fn test_cached_reader_sees_new_commits() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        
+        index.add_document("chunk1", "first commit", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        assert_eq!(index.search("first", &SearchConfig::default()).unwrap().hits.len(), 1);
+        
+        // The same reader picks up a later commit without being rebuilt
+        index.add_document("chunk2", "second commit", &ChunkMetadata::default()).unwrap();
+        index.commit().unwrap();
+        let results = index.search("second", &SearchConfig::default()).unwrap().hits;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk_id, "chunk2");
+        assert_eq!(index.doc_count(), 2);
     }
     
     #[test]
     fn test_delete_document() {
         let temp_dir = TempDir::new().unwrap();
-        let mut index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
         
-        index.add_document("chunk1", "hello world").unwrap();
+        index.add_document("chunk1", "hello world", &ChunkMetadata::default()).unwrap();
         index.commit().unwrap();
         
         assert_eq!(index.doc_count(), 1);
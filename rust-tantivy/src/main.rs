@@ -2,40 +2,273 @@
 //! 
 //! Standalone Rust service for lexical BM25 search using Tantivy.
 //! Provides HTTP API for indexing and searching text chunks.
+//!
+//! Index and delete requests only stage changes. They are committed by a
+//! background task every `TANTIVY_COMMIT_INTERVAL_MS` (default 1000), as soon
+//! as `TANTIVY_COMMIT_MAX_PENDING` ops (default 1000) are staged, or on
+//! `POST /index/flush`. Searches and `/health` only see committed changes, so
+//! a write may take up to one commit interval to become visible. Re-sending a
+//! chunk whose committed text and metadata are identical is a no-op, counted
+//! as `unchanged` in the response.
+//!
+//! `POST /index/batch/stream` takes newline-delimited `/index` requests and
+//! commits every `?commit_every=` lines (default 1000), streaming progress back.
+//! `GET /index/export` writes every live chunk in that same line format, so a
+//! backup is restored by posting it to the streaming endpoint.
+//!
+//! Every commit adds a segment. `POST /index/optimize` merges them back into
+//! one; it is I/O heavy and writes wait until it finishes, so run it during
+//! low-traffic windows.
+//!
+//! When `RICE_API_KEY` is set, every route except `/health` requires an
+//! `Authorization: Bearer <key>` header.
+//!
+//! `RICE_MAX_CONCURRENT_WRITES` caps how many index/delete requests run at
+//! once; extra ones queue, and past `RICE_MAX_QUEUED_WRITES` (default 64)
+//! waiting requests get 429. Searches and reads are never limited.
+//!
+//! JSON bodies on the index and delete routes are capped at `RICE_MAX_BODY_MB`
+//! (default 16); larger ones get a JSON 413. `/index/batch/stream` has no
+//! overall cap, only the same limit per line, so use it for bigger uploads.
+//! Search bodies are capped at 64 KB.
+//!
+//! The index writer heap is `TANTIVY_WRITER_HEAP_MB` (default 50) split across
+//! `TANTIVY_WRITER_THREADS` threads (default: chosen from the CPU count).
+//!
+//! `TANTIVY_TOKENIZER` picks the text analyzer when a new index is created:
+//! `code` (the default) also indexes CamelCase parts of identifiers,
+//! `code_stem` adds English stemming and `default` is Tantivy's plain
+//! analyzer. Existing indexes keep the analyzer they were created with; to
+//! switch one, set the variable, clear the index and reindex. `/health`
+//! reports the analyzer in use as `tokenizer`.
 
 mod index;
 mod search;
+mod tokenizer;
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::Body,
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{delete, get, post},
-    Json, Router,
+    Extension, Json, Router,
 };
+use futures_util::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::index::TantivyIndex;
-use crate::search::{filter_by_score, SearchConfig};
+use crate::index::{ChunkMetadata, IndexError, StoredChunk, TantivyIndex, DEFAULT_STORE};
+use crate::search::{filter_by_score, normalize_scores, MinMatch, SearchConfig};
 
 /// Application state shared across handlers
 struct AppState {
-    index: RwLock<TantivyIndex>,
+    /// Searches use its reader directly; only writes contend for its writer lock
+    index: TantivyIndex,
+    commit_policy: CommitPolicy,
+    /// Bearer token required on non-health routes; no auth when unset
+    api_key: Option<String>,
+    /// Directory holding the index files
+    data_dir: PathBuf,
+    /// Bound on concurrent index/delete requests; unlimited when unset
+    write_limit: Option<WriteLimit>,
+    /// Largest JSON body accepted by the index and delete routes
+    max_body_bytes: usize,
+}
+
+/// Default for `RICE_MAX_BODY_MB`
+const DEFAULT_MAX_BODY_MB: usize = 16;
+
+/// Search bodies are a query and a few options, so they get a tight fixed limit
+const SEARCH_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// `RICE_MAX_BODY_MB` in bytes, falling back to the default when unset or invalid
+fn max_body_bytes_from_env() -> usize {
+    let mb = std::env::var("RICE_MAX_BODY_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|mb| *mb > 0)
+        .unwrap_or(DEFAULT_MAX_BODY_MB);
+    mb.saturating_mul(1024 * 1024)
+}
+
+/// Caps concurrent write requests so a client scan can't starve searches
+///
+/// Requests over the limit wait for a slot; once `max_queued` are already
+/// waiting, further ones are rejected with 429.
+struct WriteLimit {
+    permits: Arc<Semaphore>,
+    max_queued: usize,
+    queued: AtomicUsize,
+}
+
+impl WriteLimit {
+    fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            max_queued,
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// `RICE_MAX_CONCURRENT_WRITES` enables the limit, `RICE_MAX_QUEUED_WRITES`
+    /// (default 64) bounds the wait queue
+    fn from_env() -> Option<Self> {
+        let max_concurrent = std::env::var("RICE_MAX_CONCURRENT_WRITES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)?;
+        let max_queued = std::env::var("RICE_MAX_QUEUED_WRITES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(64);
+
+        Some(Self::new(max_concurrent, max_queued))
+    }
+}
+
+/// A write slot, shared with handlers whose work outlives their response
+///
+/// The slot frees once the middleware and every handler holding a clone are done.
+#[derive(Clone)]
+struct WritePermit {
+    _slot: Arc<OwnedSemaphorePermit>,
+}
+
+/// Decrements the wait-queue length even if the waiting request is dropped
+struct QueuedWrite<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedWrite<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// When staged index changes get committed
+struct CommitPolicy {
+    /// How often the background task commits pending changes
+    interval: Duration,
+    /// Commit immediately once this many ops are staged
+    max_pending: usize,
+}
+
+impl CommitPolicy {
+    fn from_env() -> Self {
+        let interval_ms = std::env::var("TANTIVY_COMMIT_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|ms| *ms > 0)
+            .unwrap_or(1000);
+        let max_pending = std::env::var("TANTIVY_COMMIT_MAX_PENDING")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1000);
+
+        Self {
+            interval: Duration::from_millis(interval_ms),
+            max_pending,
+        }
+    }
+
+    /// Commit now if enough ops have been staged since the last commit
+    fn commit_if_due(&self, index: &TantivyIndex) -> Result<(), IndexError> {
+        if index.pending_ops() >= self.max_pending {
+            index.commit()?;
+        }
+        Ok(())
+    }
 }
 
 // ============================================================================
 // Request/Response Types
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 struct IndexRequest {
     chunk_id: String,
     text: String,
+    /// Store (namespace) to index into, e.g. the client's org_id
+    #[serde(default = "default_store")]
+    store: String,
+    // Optional source metadata, empty/zero when omitted
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    language: String,
+    #[serde(default)]
+    start_line: u64,
+    #[serde(default)]
+    end_line: u64,
+}
+
+fn default_store() -> String {
+    DEFAULT_STORE.to_string()
+}
+
+/// Exports use the request shape so they can be indexed again as-is
+impl From<StoredChunk> for IndexRequest {
+    fn from(chunk: StoredChunk) -> Self {
+        Self {
+            chunk_id: chunk.chunk_id,
+            text: chunk.text,
+            store: chunk.metadata.store,
+            path: chunk.metadata.path,
+            language: chunk.metadata.language,
+            start_line: chunk.metadata.start_line,
+            end_line: chunk.metadata.end_line,
+        }
+    }
+}
+
+/// Validate every chunk of a batch, naming the first invalid one
+fn validate_batch(chunks: Vec<IndexRequest>) -> Result<Vec<IndexRequest>, (StatusCode, String)> {
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(position, chunk)| {
+            chunk
+                .validated()
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("chunks[{}]: {}", position, e)))
+        })
+        .collect()
+}
+
+/// Ids of chunks whose text is empty, which are indexed but can never match
+fn empty_text_ids(chunks: &[IndexRequest]) -> Vec<String> {
+    chunks
+        .iter()
+        .filter(|chunk| chunk.text.trim().is_empty())
+        .map(|chunk| chunk.chunk_id.clone())
+        .collect()
+}
+
+impl IndexRequest {
+    /// Reject chunks that could never be looked up or deleted again
+    fn validated(self) -> Result<Self, String> {
+        if self.chunk_id.trim().is_empty() {
+            return Err("chunk_id must not be empty".to_string());
+        }
+        Ok(self)
+    }
+
+    fn metadata(&self) -> ChunkMetadata {
+        ChunkMetadata {
+            store: self.store.clone(),
+            path: self.path.clone(),
+            language: self.language.clone(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,35 +280,181 @@ struct BatchIndexRequest {
 struct SearchRequest {
     query: String,
     #[serde(flatten)]
-    config: Option<SearchConfig>,
-    // Legacy fields for backward compatibility
+    config: SearchConfig,
+}
+
+/// `GET /search` parameters; everything else takes its `SearchConfig` default
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    q: String,
     limit: Option<usize>,
+    offset: Option<usize>,
     min_score: Option<f32>,
+    store: Option<String>,
+    min_match: Option<MinMatch>,
+    #[serde(default)]
+    normalize_scores: bool,
+    #[serde(default)]
+    highlight: bool,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default)]
+    whole_word: bool,
+}
+
+impl SearchParams {
+    fn into_request(self) -> SearchRequest {
+        let defaults = SearchConfig::default();
+        SearchRequest {
+            query: self.q,
+            config: SearchConfig {
+                limit: self.limit.unwrap_or(defaults.limit),
+                offset: self.offset.unwrap_or(defaults.offset),
+                min_score: self.min_score,
+                store: self.store,
+                min_match: self.min_match,
+                normalize_scores: self.normalize_scores,
+                highlight: self.highlight,
+                case_sensitive: self.case_sensitive,
+                whole_word: self.whole_word,
+                ..defaults
+            },
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
 struct SearchResult {
     chunk_id: String,
     score: f32,
+    store: String,
+    path: String,
+    language: String,
+    start_line: u64,
+    end_line: u64,
+    /// Line of the first matched term, only with `highlight`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_line: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
 struct SearchResponse {
     results: Vec<SearchResult>,
     query: String,
+    /// Number of chunks matching the query, regardless of the page size
     total_hits: usize,
+    /// Number of results in this response
+    returned: usize,
+    offset: usize,
+}
+
+/// Chunks committed per batch by `/index/batch/stream` unless `?commit_every=` says otherwise
+const DEFAULT_STREAM_COMMIT_EVERY: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+struct StreamIndexQuery {
+    commit_every: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchDeleteRequest {
+    /// Exact chunk ids to delete
+    #[serde(default)]
+    chunk_ids: Vec<String>,
+    /// Delete every chunk id starting with this prefix (e.g. `path#`)
+    prefix: Option<String>,
+    /// Only delete prefix matches belonging to this store
+    store: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClearQuery {
+    /// Only clear this store instead of the whole index
+    store: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunksQuery {
+    path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PathsQuery {
+    /// Only list paths with chunks in this store
+    store: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    /// Only export chunks in this store
+    store: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PathsResponse {
+    store: Option<String>,
+    paths: Vec<String>,
+    total: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ChunkEntry {
+    chunk_id: String,
+    start_line: u64,
+    end_line: u64,
+    indexed_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChunksResponse {
+    path: String,
+    chunks: Vec<ChunkEntry>,
+    total: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct DocumentResponse {
+    chunk_id: String,
+    text: String,
+    store: String,
+    path: String,
+    language: String,
+    start_line: u64,
+    end_line: u64,
+    indexed_at: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 struct HealthResponse {
     status: String,
     indexed_docs: u64,
+    segments: usize,
+    /// Approximate on-disk size of the index directory
+    index_size_bytes: u64,
+    data_dir: String,
+    /// Analyzer of the `text` field, which clients' queries are parsed with
+    tokenizer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stores: Option<BTreeMap<String, u64>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HealthQuery {
+    /// Include a per-store document count breakdown
+    #[serde(default)]
+    stores: bool,
 }
 
 #[derive(Debug, Serialize)]
 struct IndexResponse {
     status: String,
+    /// Chunks written to the index
     indexed: usize,
+    /// Chunks skipped because the index already held identical content
+    unchanged: usize,
+    /// Chunks indexed with empty text, which can never match a search
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    empty_text: Vec<String>,
 }
 
 // ============================================================================
@@ -83,14 +462,36 @@ struct IndexResponse {
 // ============================================================================
 
 /// Health check endpoint
-async fn health(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let index = state.index.read().await;
-    let doc_count = index.doc_count();
+async fn health(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HealthQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let index = &state.index;
+    let stores = if params.stores {
+        let counts = index
+            .store_doc_counts()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        Some(counts)
+    } else {
+        None
+    };
+    let (doc_count, segments) = (index.doc_count(), index.segment_count());
+    
+    // Walking the directory is blocking I/O, so keep it off the async workers
+    let data_dir = state.data_dir.clone();
+    let index_size_bytes = tokio::task::spawn_blocking(move || dir_size(&data_dir))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
-    Json(HealthResponse {
+    Ok(Json(HealthResponse {
         status: "healthy".to_string(),
         indexed_docs: doc_count,
-    })
+        segments,
+        index_size_bytes,
+        data_dir: state.data_dir.display().to_string(),
+        tokenizer: index.tokenizer_name(),
+        stores,
+    }))
 }
 
 /// Index a single chunk
@@ -98,19 +499,20 @@ async fn index_chunk(
     State(state): State<Arc<AppState>>,
     Json(req): Json<IndexRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut index = state.index.write().await;
-    
-    index
-        .add_document(&req.chunk_id, &req.text)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    
-    index
-        .commit()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let req = req.validated().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let empty_text = empty_text_ids(std::slice::from_ref(&req));
+    let (indexed, unchanged) = run_write(&state, move |state| {
+        let counts = add_chunks(&state.index, [req])?;
+        state.commit_policy.commit_if_due(&state.index)?;
+        Ok(counts)
+    })
+    .await?;
     
     Ok(Json(IndexResponse {
         status: "success".to_string(),
-        indexed: 1,
+        indexed,
+        unchanged,
+        empty_text,
     }))
 }
 
@@ -119,42 +521,304 @@ async fn batch_index(
     State(state): State<Arc<AppState>>,
     Json(req): Json<BatchIndexRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut index = state.index.write().await;
-    let count = req.chunks.len();
-    
-    for chunk in req.chunks {
-        index
-            .add_document(&chunk.chunk_id, &chunk.text)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    }
-    
-    index
-        .commit()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let chunks = validate_batch(req.chunks)?;
+    let empty_text = empty_text_ids(&chunks);
+    let (indexed, unchanged) = run_write(&state, move |state| {
+        let counts = add_chunks(&state.index, chunks)?;
+        state.commit_policy.commit_if_due(&state.index)?;
+        Ok(counts)
+    })
+    .await?;
     
     Ok(Json(IndexResponse {
         status: "success".to_string(),
-        indexed: count,
+        indexed,
+        unchanged,
+        empty_text,
     }))
 }
 
+/// Index newline-delimited chunks as they arrive, committing every `commit_every`
+///
+/// Each line has the shape of a `/index` request. The response streams one
+/// JSON line per committed batch, one per malformed, oversized or empty
+/// `chunk_id` line (which is skipped), a warning per chunk with empty text
+/// (which is indexed) and a final summary listing the skipped lines. The body
+/// is only read as fast as batches are indexed and no line may exceed
+/// `RICE_MAX_BODY_MB`, so memory stays bounded however large the upload is.
+/// The write slot is held until the last batch is committed.
+async fn batch_index_stream(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<StreamIndexQuery>,
+    permit: Option<Extension<WritePermit>>,
+    body: Body,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let commit_every = params.commit_every.unwrap_or(DEFAULT_STREAM_COMMIT_EVERY);
+    if commit_every == 0 {
+        return Err((StatusCode::BAD_REQUEST, "commit_every must be at least 1".to_string()));
+    }
+
+    let (events, receiver) = mpsc::channel(16);
+    let permit = permit.map(|Extension(permit)| permit);
+    tokio::spawn(async move {
+        index_ndjson(state, body, commit_every, events).await;
+        drop(permit);
+    });
+
+    let lines = stream::unfold(receiver, |mut receiver| async move {
+        let line = receiver.recv().await?;
+        Some((Ok::<_, std::convert::Infallible>(line), receiver))
+    });
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], Body::from_stream(lines)))
+}
+
+/// Read, index and report on an ndjson body; stops early if the client goes away
+async fn index_ndjson(
+    state: Arc<AppState>,
+    body: Body,
+    commit_every: usize,
+    events: mpsc::Sender<String>,
+) {
+    let emit = |event: serde_json::Value| {
+        let events = events.clone();
+        async move { events.send(format!("{}\n", event)).await.is_ok() }
+    };
+
+    let max_line_bytes = state.max_body_bytes;
+    let too_long = format!("line is longer than the {} byte limit", max_line_bytes);
+    // Set once an over-long line is reported, until its terminating newline arrives
+    let mut discarding = false;
+
+    let mut body = body.into_data_stream();
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut batch: Vec<IndexRequest> = Vec::new();
+    let (mut line_number, mut indexed, mut unchanged) = (0usize, 0usize, 0usize);
+    // Every dropped line, repeated in the summary so callers needn't collect them
+    let mut errors: Vec<serde_json::Value> = Vec::new();
+
+    loop {
+        let finished = match body.next().await {
+            Some(Ok(bytes)) => {
+                let mut bytes = &bytes[..];
+                if discarding {
+                    match bytes.iter().position(|&b| b == b'\n') {
+                        Some(end) => {
+                            bytes = &bytes[end + 1..];
+                            discarding = false;
+                        }
+                        None => bytes = &[],
+                    }
+                }
+                buffer.extend_from_slice(bytes);
+                false
+            }
+            Some(Err(e)) => {
+                emit(serde_json::json!({ "status": "error", "error": e.to_string() })).await;
+                return;
+            }
+            None => true,
+        };
+
+        // Complete lines only; at the end of the body the remainder is the last line
+        let mut lines = Vec::new();
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            lines.push(buffer.drain(..=end).collect::<Vec<u8>>());
+        }
+        if finished && !buffer.is_empty() {
+            lines.push(std::mem::take(&mut buffer));
+        }
+
+        for line in lines {
+            line_number += 1;
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            let parsed = if line.len() > max_line_bytes {
+                Err(too_long.clone())
+            } else {
+                serde_json::from_slice::<IndexRequest>(&line).map_err(|e| e.to_string())
+            };
+            match parsed.and_then(IndexRequest::validated) {
+                Ok(chunk) => {
+                    if chunk.text.trim().is_empty() {
+                        let event = serde_json::json!({
+                            "status": "warning",
+                            "line": line_number,
+                            "warning": "text is empty, so the chunk can never match a search"
+                        });
+                        if !emit(event).await {
+                            return;
+                        }
+                    }
+                    batch.push(chunk);
+                }
+                Err(error) => {
+                    errors.push(serde_json::json!({ "line": line_number, "error": error }));
+                    let event = serde_json::json!({
+                        "status": "error",
+                        "line": line_number,
+                        "error": error
+                    });
+                    if !emit(event).await {
+                        return;
+                    }
+                }
+            }
+
+            if batch.len() >= commit_every {
+                match index_and_commit(&state, std::mem::take(&mut batch)).await {
+                    Ok((written, skipped)) => {
+                        indexed += written;
+                        unchanged += skipped;
+                    }
+                    Err((_, e)) => {
+                        emit(serde_json::json!({ "status": "error", "error": e })).await;
+                        return;
+                    }
+                }
+                let event = serde_json::json!({
+                    "status": "progress",
+                    "indexed": indexed,
+                    "unchanged": unchanged
+                });
+                if !emit(event).await {
+                    return;
+                }
+            }
+        }
+
+        if finished {
+            break;
+        }
+
+        // An unfinished line over the limit is reported now and dropped as it arrives
+        if buffer.len() > max_line_bytes {
+            line_number += 1;
+            buffer.clear();
+            discarding = true;
+            errors.push(serde_json::json!({ "line": line_number, "error": too_long }));
+            let event = serde_json::json!({
+                "status": "error",
+                "line": line_number,
+                "error": too_long
+            });
+            if !emit(event).await {
+                return;
+            }
+        }
+    }
+
+    if !batch.is_empty() {
+        match index_and_commit(&state, batch).await {
+            Ok((written, skipped)) => {
+                indexed += written;
+                unchanged += skipped;
+            }
+            Err((_, e)) => {
+                emit(serde_json::json!({ "status": "error", "error": e })).await;
+                return;
+            }
+        }
+    }
+    let summary = serde_json::json!({
+        "status": "success",
+        "indexed": indexed,
+        "unchanged": unchanged,
+        "errors": errors
+    });
+    emit(summary).await;
+}
+
+/// Add a batch of chunks and commit them, returning the written and unchanged counts
+async fn index_and_commit(
+    state: &Arc<AppState>,
+    chunks: Vec<IndexRequest>,
+) -> Result<(usize, usize), (StatusCode, String)> {
+    run_write(state, move |state| {
+        let counts = add_chunks(&state.index, chunks)?;
+        state.index.commit()?;
+        Ok(counts)
+    })
+    .await
+}
+
+/// Stage chunks, returning how many were written and how many were unchanged
+fn add_chunks(
+    index: &TantivyIndex,
+    chunks: impl IntoIterator<Item = IndexRequest>,
+) -> Result<(usize, usize), IndexError> {
+    let (mut indexed, mut unchanged) = (0, 0);
+    for chunk in chunks {
+        if index.add_document(&chunk.chunk_id, &chunk.text, &chunk.metadata())? {
+            indexed += 1;
+        } else {
+            unchanged += 1;
+        }
+    }
+    Ok((indexed, unchanged))
+}
+
+/// Run index writes on the blocking pool
+///
+/// Waiting for the writer lock or a commit then never stalls the async
+/// workers that serve searches.
+async fn run_write<T, F>(state: &Arc<AppState>, write: F) -> Result<T, (StatusCode, String)>
+where
+    T: Send + 'static,
+    F: FnOnce(&AppState) -> Result<T, IndexError> + Send + 'static,
+{
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || write(&state))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Unparseable queries (bad syntax, unknown or unindexed fields) and a `min_match`
+/// above the query's term count are the caller's fault
+fn search_error(e: IndexError) -> (StatusCode, String) {
+    match e {
+        IndexError::QueryParse(_) | IndexError::InvalidQuery(_) => {
+            (StatusCode::BAD_REQUEST, e.to_string())
+        }
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
 /// Search for chunks using BM25
 async fn search_chunks(
     State(state): State<Arc<AppState>>,
     Json(req): Json<SearchRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let index = state.index.read().await;
+    Ok(Json(run_search(&state, req)?))
+}
 
-    // Use config if provided, otherwise use legacy fields
-    let config = req.config.unwrap_or_else(|| SearchConfig {
-        limit: req.limit.unwrap_or(10),
-        min_score: req.min_score,
-        highlight: false,
-    });
+/// `GET /search?q=...` for browsers and curl; POST remains the full-featured form
+async fn search_chunks_get(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if params.q.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "q must not be empty".to_string()));
+    }
+    Ok(Json(run_search(&state, params.into_request())?))
+}
 
-    let mut results = index
-        .search(&req.query, config.limit)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+fn run_search(
+    state: &AppState,
+    req: SearchRequest,
+) -> Result<SearchResponse, (StatusCode, String)> {
+    let config = req.config;
+    config
+        .validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let page = state.index.search(&req.query, &config).map_err(search_error)?;
+    let (mut results, total_hits) = (page.hits, page.total_hits);
+
+    if config.normalize_scores {
+        results = normalize_scores(results, page.top_score);
+    }
 
     // Apply minimum score filter if specified
     if let Some(min_score) = config.min_score {
@@ -162,19 +826,151 @@ async fn search_chunks(
     }
 
     let search_results: Vec<SearchResult> = results
-        .iter()
-        .map(|(chunk_id, score)| SearchResult {
-            chunk_id: chunk_id.clone(),
-            score: *score,
+        .into_iter()
+        .map(|hit| SearchResult {
+            chunk_id: hit.chunk_id,
+            score: hit.score,
+            store: hit.metadata.store,
+            path: hit.metadata.path,
+            language: hit.metadata.language,
+            start_line: hit.metadata.start_line,
+            end_line: hit.metadata.end_line,
+            match_line: hit.match_line,
         })
         .collect();
 
-    let total = search_results.len();
+    let returned = search_results.len();
 
-    Ok(Json(SearchResponse {
+    Ok(SearchResponse {
         results: search_results,
         query: req.query,
-        total_hits: total,
+        total_hits,
+        returned,
+        offset: config.offset,
+    })
+}
+
+/// Count the chunks matching a query without fetching any of them
+///
+/// Takes the same body as `/search`; the store and language filters apply,
+/// paging and score options are ignored.
+async fn count_chunks(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SearchRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let config = req.config;
+    config
+        .validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let count = state
+        .index
+        .count(&req.query, &config)
+        .map_err(search_error)?;
+
+    Ok(Json(serde_json::json!({
+        "count": count,
+        "query": req.query,
+        "store": config.store
+    })))
+}
+
+/// List the chunks indexed for a single file path
+async fn list_chunks(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ChunksQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let index = &state.index;
+
+    let chunks: Vec<ChunkEntry> = index
+        .chunks_for_path(&params.path)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .map(|chunk| ChunkEntry {
+            chunk_id: chunk.chunk_id,
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            indexed_at: chunk.indexed_at,
+        })
+        .collect();
+
+    let total = chunks.len();
+
+    Ok(Json(ChunksResponse {
+        path: params.path,
+        chunks,
+        total,
+    }))
+}
+
+/// List every file path with indexed chunks, so clients can reconcile with disk
+async fn list_paths(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PathsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let paths = state
+        .index
+        .indexed_paths(params.store.as_deref())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let total = paths.len();
+    Ok(Json(PathsResponse {
+        store: params.store,
+        paths,
+        total,
+    }))
+}
+
+/// Stream every live chunk as ndjson, one `/index` request per line
+///
+/// Posting the output to `/index/batch/stream` restores it into another
+/// index. Chunks are read on the blocking pool as the client consumes them;
+/// a read error ends the body early instead of truncating it silently.
+async fn export_index(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ExportQuery>,
+) -> impl IntoResponse {
+    let (lines, receiver) = mpsc::channel::<Result<String, IndexError>>(64);
+    tokio::task::spawn_blocking(move || {
+        let result = state.index.for_each_chunk(params.store.as_deref(), |chunk| {
+            let line = serde_json::to_string(&IndexRequest::from(chunk))
+                .expect("index requests always serialize");
+            lines.blocking_send(Ok(line + "\n")).is_ok()
+        });
+        if let Err(e) = result {
+            tracing::error!("Export failed: {}", e);
+            let _ = lines.blocking_send(Err(e));
+        }
+    });
+
+    let body = stream::unfold(receiver, |mut receiver| async move {
+        let line = receiver.recv().await?;
+        Some((line, receiver))
+    });
+    ([(header::CONTENT_TYPE, "application/x-ndjson")], Body::from_stream(body))
+}
+
+/// Fetch the stored text and metadata of a single chunk
+async fn get_document(
+    State(state): State<Arc<AppState>>,
+    Path(chunk_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let index = &state.index;
+
+    let doc = index
+        .get_document(&chunk_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Chunk not found: {}", chunk_id)))?;
+
+    Ok(Json(DocumentResponse {
+        chunk_id: doc.chunk_id,
+        text: doc.text,
+        store: doc.metadata.store,
+        path: doc.metadata.path,
+        language: doc.metadata.language,
+        start_line: doc.metadata.start_line,
+        end_line: doc.metadata.end_line,
+        indexed_at: doc.indexed_at,
     }))
 }
 
@@ -183,15 +979,12 @@ async fn delete_chunk(
     State(state): State<Arc<AppState>>,
     Path(chunk_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut index = state.index.write().await;
-    
-    index
-        .delete_document(&chunk_id)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    
-    index
-        .commit()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let id = chunk_id.clone();
+    run_write(&state, move |state| {
+        state.index.delete_document(&id)?;
+        state.commit_policy.commit_if_due(&state.index)
+    })
+    .await?;
     
     Ok(Json(serde_json::json!({
         "status": "deleted",
@@ -199,21 +992,304 @@ async fn delete_chunk(
     })))
 }
 
-/// Clear the entire index
+/// Delete many chunks at once, by explicit id and/or by chunk id prefix
+async fn batch_delete(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BatchDeleteRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if req.chunk_ids.is_empty() && req.prefix.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Either chunk_ids or prefix is required".to_string(),
+        ));
+    }
+    if req.prefix.as_deref() == Some("") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "prefix must not be empty; use /index/clear to delete everything".to_string(),
+        ));
+    }
+
+    let deleted = run_write(&state, move |state| {
+        let mut deleted = 0;
+        for chunk_id in &req.chunk_ids {
+            state.index.delete_document(chunk_id)?;
+            deleted += 1;
+        }
+        if let Some(prefix) = &req.prefix {
+            deleted += state.index.delete_prefix(prefix, req.store.as_deref())?;
+        }
+        state.commit_policy.commit_if_due(&state.index)?;
+        Ok(deleted)
+    })
+    .await?;
+    
+    Ok(Json(serde_json::json!({
+        "status": "deleted",
+        "deleted": deleted
+    })))
+}
+
+/// Commit all staged changes now instead of waiting for the background task
+async fn flush_index(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let pending = state.index.pending_ops();
+    
+    run_write(&state, |state| state.index.commit()).await?;
+    
+    Ok(Json(serde_json::json!({
+        "status": "flushed",
+        "committed": pending
+    })))
+}
+
+/// Merge all segments into one, physically removing deleted documents
+///
+/// Merging rewrites the whole index, so call this in a low-traffic window.
+/// `deleted_docs_after` is only non-zero if deletes landed during the merge.
+/// The writer is only locked while the merge starts; searches keep using
+/// the current segments until it finishes.
+async fn optimize_index(
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // Counted before the merge's own commit adds anything staged since the last one
+    let segments_before = state.index.segment_count();
+    let deleted_docs_before = state.index.deleted_doc_count();
+    run_write(&state, |state| state.index.optimize()).await?;
+    
+    Ok(Json(serde_json::json!({
+        "status": "optimized",
+        "segments_before": segments_before,
+        "segments_after": state.index.segment_count(),
+        "deleted_docs_before": deleted_docs_before,
+        "deleted_docs_after": state.index.deleted_doc_count()
+    })))
+}
+
+/// Clear the entire index, or a single store with `?store=`
 async fn clear_index(
     State(state): State<Arc<AppState>>,
+    Query(params): Query<ClearQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut index = state.index.write().await;
+    let Some(store) = params.store else {
+        run_write(&state, |state| state.index.clear()).await?;
+        
+        return Ok(Json(serde_json::json!({
+            "status": "cleared"
+        })));
+    };
     
-    index
-        .clear()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let cleared = store.clone();
+    let deleted = run_write(&state, move |state| state.index.clear_store(&cleared)).await?;
     
     Ok(Json(serde_json::json!({
-        "status": "cleared"
+        "status": "cleared",
+        "store": store,
+        "deleted": deleted
     })))
 }
 
+/// Total size of the files under `dir`, skipping anything unreadable
+///
+/// Segment files can be merged away mid-walk, so this is an approximation.
+fn dir_size(dir: &std::path::Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+// ============================================================================
+// Middleware
+// ============================================================================
+
+/// Replace axum's plain-text 413 with a JSON error naming the limit and the way around it
+async fn explain_body_limit(State(state): State<Arc<AppState>>, response: Response) -> Response {
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return response;
+    }
+    let error = format!(
+        "Request body exceeds the {} MB limit; send large batches to \
+         /index/batch/stream as ndjson or raise RICE_MAX_BODY_MB",
+        state.max_body_bytes / (1024 * 1024)
+    );
+    let body = serde_json::json!({ "error": error, "limit_bytes": state.max_body_bytes });
+    (StatusCode::PAYLOAD_TOO_LARGE, Json(body)).into_response()
+}
+
+/// Admit a write request once a slot under the write limit is free
+async fn limit_writes(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    let Some(limit) = &state.write_limit else {
+        return Ok(next.run(request).await);
+    };
+
+    let permit = match limit.permits.clone().try_acquire_owned() {
+        Ok(permit) => permit,
+        Err(_) => {
+            let queued = QueuedWrite(&limit.queued);
+            if limit.queued.fetch_add(1, Ordering::SeqCst) >= limit.max_queued {
+                return Err((
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Too many pending write requests, retry later".to_string(),
+                ));
+            }
+            let permit = limit.permits.clone().acquire_owned().await;
+            drop(queued);
+            permit.expect("write semaphore is never closed")
+        }
+    };
+
+    let mut request = request;
+    request.extensions_mut().insert(WritePermit { _slot: Arc::new(permit) });
+    Ok(next.run(request).await)
+}
+
+/// Reject requests without `Authorization: Bearer <RICE_API_KEY>`
+async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    let Some(expected) = state.api_key.as_deref() else {
+        return Ok(next.run(request).await);
+    };
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err((
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid API key".to_string(),
+        )),
+    }
+}
+
+/// Compare secrets without short-circuiting on the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ============================================================================
+// Background Tasks
+// ============================================================================
+
+/// Periodically commit staged changes so they become visible to searches
+async fn run_background_commits(state: Arc<AppState>) {
+    let mut ticker = tokio::time::interval(state.commit_policy.interval);
+
+    loop {
+        ticker.tick().await;
+
+        // Avoid locking the writer when there is nothing to commit
+        let pending = state.index.pending_ops();
+        if pending == 0 {
+            continue;
+        }
+
+        match run_write(&state, |state| state.index.commit()).await {
+            Ok(()) => tracing::debug!("Committed {} pending ops", pending),
+            Err((_, e)) => tracing::error!("Background commit failed: {}", e),
+        }
+    }
+}
+
+/// Build the HTTP router with all routes and middleware
+fn build_router(state: Arc<AppState>) -> Router {
+    // Index and delete routes share the optional write limit; reads never wait on it
+    let writes = Router::new()
+        .route("/index", post(index_chunk))
+        .route("/index/batch", post(batch_index))
+        .route("/index/batch/stream", post(batch_index_stream))
+        .route("/index/delete/batch", post(batch_delete))
+        .route("/index/{chunk_id}", delete(delete_chunk))
+        .route("/index/clear", post(clear_index))
+        // The ndjson stream reads its body incrementally, so the limit never applies to it
+        .route_layer(DefaultBodyLimit::max(state.max_body_bytes))
+        .route_layer(middleware::map_response_with_state(state.clone(), explain_body_limit))
+        .route_layer(middleware::from_fn_with_state(state.clone(), limit_writes));
+
+    let searches = Router::new()
+        .route("/search", post(search_chunks).get(search_chunks_get))
+        .route("/search/count", post(count_chunks))
+        .route_layer(DefaultBodyLimit::max(SEARCH_MAX_BODY_BYTES));
+
+    let mut protected = Router::new()
+        .route("/index/chunks", get(list_chunks))
+        .route("/index/paths", get(list_paths))
+        .route("/index/export", get(export_index))
+        .route("/index/flush", post(flush_index))
+        .route("/index/optimize", post(optimize_index))
+        .route("/index/{chunk_id}/doc", get(get_document))
+        .merge(searches)
+        .merge(writes);
+
+    // Health stays public so orchestrators can probe it without credentials
+    if state.api_key.is_some() {
+        protected = protected.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_api_key,
+        ));
+    }
+
+    Router::new()
+        .route("/health", get(health))
+        .merge(protected)
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods(Any)
+                .allow_headers(Any),
+        )
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+/// Resolve when the process receives Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received, draining connections");
+}
+
 // ============================================================================
 // Main
 // ============================================================================
@@ -237,26 +1313,684 @@ async fn main() {
 
     // Create index
     let tantivy_index = TantivyIndex::new(&data_dir).expect("Failed to create Tantivy index");
+    tracing::info!("Text tokenizer: {}", tantivy_index.tokenizer_name());
+
+    let commit_policy = CommitPolicy::from_env();
+    tracing::info!(
+        "Committing every {}ms or after {} pending ops",
+        commit_policy.interval.as_millis(),
+        commit_policy.max_pending
+    );
+
+    let api_key = std::env::var("RICE_API_KEY").ok().filter(|key| !key.is_empty());
+    if api_key.is_some() {
+        tracing::info!("API key authentication enabled");
+    }
+
+    let write_limit = WriteLimit::from_env();
+    if let Some(limit) = &write_limit {
+        tracing::info!(
+            "Limiting to {} concurrent writes with up to {} queued",
+            limit.permits.available_permits(),
+            limit.max_queued
+        );
+    }
 
     let state = Arc::new(AppState {
-        index: RwLock::new(tantivy_index),
+        index: tantivy_index,
+        commit_policy,
+        api_key,
+        data_dir: PathBuf::from(&data_dir),
+        write_limit,
+        max_body_bytes: max_body_bytes_from_env(),
     });
 
-    // Build router
-    let app = Router::new()
-        .route("/health", get(health))
-        .route("/index", post(index_chunk))
-        .route("/index/batch", post(batch_index))
-        .route("/index/{chunk_id}", delete(delete_chunk))
-        .route("/index/clear", post(clear_index))
-        .route("/search", post(search_chunks))
-        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any))
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    tokio::spawn(run_background_commits(state.clone()));
+
+    let app = build_router(state.clone());
 
     let addr = format!("{}:{}", host, port);
     tracing::info!("Starting BM25 search service on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+
+    // Commit whatever is still staged so it isn't lost on exit
+    let pending = state.index.pending_ops();
+    match state.index.commit() {
+        Ok(()) => tracing::info!("Flushed {} pending ops on shutdown", pending),
+        Err(e) => tracing::error!("Final commit failed, {} pending ops lost: {}", pending, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use serde_json::{json, Value};
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    fn test_state(temp_dir: &TempDir) -> Arc<AppState> {
+        test_state_with_key(temp_dir, None)
+    }
+
+    fn test_state_with_key(temp_dir: &TempDir, api_key: Option<&str>) -> Arc<AppState> {
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        Arc::new(AppState {
+            index,
+            commit_policy: CommitPolicy {
+                interval: Duration::from_secs(60),
+                max_pending: 0,
+            },
+            api_key: api_key.map(str::to_string),
+            data_dir: temp_dir.path().to_path_buf(),
+            write_limit: None,
+            max_body_bytes: DEFAULT_MAX_BODY_MB * 1024 * 1024,
+        })
+    }
+
+    async fn post_json(app: Router, uri: &str, body: Value) -> (StatusCode, Value) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_batch_delete_by_ids_and_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = test_state(&temp_dir);
+        let app = build_router(state.clone());
+
+        let chunks: Vec<_> = ["a.rs#1-10", "a.rs#11-20", "b.rs#1-10", "c.rs#1-10", "c.rs#11-20"]
+            .iter()
+            .map(|id| json!({ "chunk_id": id, "text": "shared text" }))
+            .collect();
+        let (status, _) = post_json(app.clone(), "/index/batch", json!({ "chunks": chunks })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state.index.doc_count(), 5);
+
+        // Explicit ids
+        let (status, body) = post_json(
+            app.clone(),
+            "/index/delete/batch",
+            json!({ "chunk_ids": ["b.rs#1-10", "c.rs#1-10"] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["deleted"], 2);
+        assert_eq!(state.index.doc_count(), 3);
+
+        // Prefix
+        let (status, body) = post_json(
+            app.clone(),
+            "/index/delete/batch",
+            json!({ "prefix": "a.rs#" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["deleted"], 2);
+
+        let index = &state.index;
+        assert_eq!(index.doc_count(), 1);
+        assert_eq!(index.chunk_ids_with_prefix("").unwrap(), vec!["c.rs#11-20"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_and_health_scoped_by_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+
+        let chunks = json!({ "chunks": [
+            { "chunk_id": "a.rs#1-2", "text": "tokio runtime", "store": "acme" },
+            { "chunk_id": "b.rs#1-2", "text": "tokio runtime" },
+        ]});
+        post_json(app.clone(), "/index/batch", chunks).await;
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/search",
+            json!({ "query": "tokio", "store": "acme" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["results"].as_array().unwrap().len(), 1);
+        assert_eq!(body["results"][0]["store"], "acme");
+
+        let (status, body) = post_json(
+            app.clone(),
+            "/search/count",
+            json!({ "query": "tokio", "store": "acme" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["count"], 1);
+        assert_eq!(body["store"], "acme");
+
+        let (status, _) = post_json(app.clone(), "/search", json!({ "query": "nosuch:field" }))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // total_hits counts every match, not just the returned page
+        let (_, body) = post_json(app.clone(), "/search", json!({ "query": "tokio", "limit": 1 }))
+            .await;
+        assert_eq!(body["returned"], 1);
+        assert_eq!(body["total_hits"], 2);
+
+        let request = Request::get("/health?stores=true").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["indexed_docs"], 2);
+        assert_eq!(body["stores"]["acme"], 1);
+        assert_eq!(body["stores"]["public"], 1);
+        assert_eq!(body["segments"], 1);
+        assert!(body["index_size_bytes"].as_u64().unwrap() > 0);
+        assert_eq!(body["data_dir"], temp_dir.path().display().to_string());
+        assert_eq!(body["tokenizer"], "code");
+    }
+
+    #[tokio::test]
+    async fn test_reindexing_same_content_reports_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+
+        let chunks = json!({ "chunks": [
+            { "chunk_id": "a.rs#1-2", "text": "tokio runtime" },
+            { "chunk_id": "b.rs#1-2", "text": "async runtime" },
+        ]});
+        let (_, body) = post_json(app.clone(), "/index/batch", chunks).await;
+        assert_eq!(body["indexed"], 2);
+        assert_eq!(body["unchanged"], 0);
+
+        let chunks = json!({ "chunks": [
+            { "chunk_id": "a.rs#1-2", "text": "tokio runtime" },
+            { "chunk_id": "b.rs#1-2", "text": "async runtime, edited" },
+        ]});
+        let (_, body) = post_json(app.clone(), "/index/batch", chunks).await;
+        assert_eq!(body["indexed"], 1);
+        assert_eq!(body["unchanged"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_export_round_trips_into_fresh_index() {
+        let source_dir = TempDir::new().unwrap();
+        let source = build_router(test_state(&source_dir));
+
+        let chunks: Vec<Value> = (0..25)
+            .map(|i| {
+                json!({
+                    "chunk_id": format!("src/f{}.rs#1-9", i),
+                    "text": format!("fn handler_{}() {{ spawn(task_{}) }} tokio", i, i % 4),
+                    "store": if i % 5 == 0 { "acme" } else { "public" },
+                    "path": format!("src/f{}.rs", i),
+                    "language": "rust",
+                    "start_line": 1,
+                    "end_line": 9
+                })
+            })
+            .collect();
+        post_json(source.clone(), "/index/batch", json!({ "chunks": chunks })).await;
+        // Deleted chunks must not come back
+        let request = Request::delete("/index/src%2Ff3.rs%231-9").body(Body::empty()).unwrap();
+        source.clone().oneshot(request).await.unwrap();
+        post_json(source.clone(), "/index/flush", json!({})).await;
+
+        let export = |uri: &str| {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let app = source.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                String::from_utf8(bytes.to_vec()).unwrap()
+            }
+        };
+        assert_eq!(export("/index/export?store=acme").await.lines().count(), 5);
+        let dump = export("/index/export").await;
+        assert_eq!(dump.lines().count(), 24);
+
+        let target_dir = TempDir::new().unwrap();
+        let target = build_router(test_state(&target_dir));
+        let request = Request::post("/index/batch/stream")
+            .body(Body::from(dump))
+            .unwrap();
+        let response = target.clone().oneshot(request).await.unwrap();
+        axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        // BM25 statistics count deleted docs until a merge drops them
+        post_json(source.clone(), "/index/optimize", json!({})).await;
+        let queries = [
+            json!({ "query": "tokio", "limit": 30 }),
+            json!({ "query": "task_1", "store": "acme", "highlight": true }),
+        ];
+        for query in queries {
+            let (_, expected) = post_json(source.clone(), "/search", query.clone()).await;
+            let (_, restored) = post_json(target.clone(), "/search", query).await;
+            assert_eq!(restored, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_get_matches_post() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+
+        let chunks = json!({ "chunks": [
+            { "chunk_id": "a.rs#1-2", "text": "tokio runtime", "store": "acme" },
+            { "chunk_id": "b.rs#1-2", "text": "tokio tokio runtime" },
+            { "chunk_id": "c.rs#1-2", "text": "async runtime" },
+        ]});
+        post_json(app.clone(), "/index/batch", chunks).await;
+
+        let get = |uri: &str| {
+            let app = app.clone();
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<Value>(&bytes).unwrap_or(Value::Null))
+            }
+        };
+
+        let (status, body) = get("/search?q=tokio%20runtime&limit=2&offset=1").await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, post_body) = post_json(
+            app.clone(),
+            "/search",
+            json!({ "query": "tokio runtime", "limit": 2, "offset": 1 }),
+        )
+        .await;
+        assert_eq!(body, post_body);
+        assert_eq!(body["offset"], 1);
+        assert_eq!(body["total_hits"], 3);
+
+        let (_, body) = get("/search?q=tokio&store=acme").await;
+        assert_eq!(body["results"][0]["chunk_id"], "a.rs#1-2");
+        assert_eq!(body["returned"], 1);
+
+        let (_, body) = get("/search?q=tokio%20runtime&min_match=100%25").await;
+        assert_eq!(body["total_hits"], 2);
+        assert_eq!(get("/search?q=tokio&min_match=2").await.0, StatusCode::BAD_REQUEST);
+
+        assert_eq!(get("/search?q=").await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(get("/search?q=tokio&limit=ten").await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(get("/search?q=tokio&limit=0").await.0, StatusCode::BAD_REQUEST);
+        let offset = format!("/search?q=tokio&offset={}", usize::MAX);
+        assert_eq!(get(&offset).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_normalized_scores_are_relative_to_the_first_page() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+
+        let chunks = json!({ "chunks": [
+            { "chunk_id": "a.rs#1-2", "text": "tokio tokio tokio runtime" },
+            { "chunk_id": "b.rs#1-2", "text": "tokio tokio runtime" },
+            { "chunk_id": "c.rs#1-2", "text": "tokio runtime" },
+        ]});
+        post_json(app.clone(), "/index/batch", chunks).await;
+
+        let page = |offset: usize| {
+            let query = json!({
+                "query": "tokio",
+                "limit": 1,
+                "offset": offset,
+                "normalize_scores": true,
+            });
+            post_json(app.clone(), "/search", query)
+        };
+        let (_, first) = page(0).await;
+        assert_eq!(first["results"][0]["score"], 1.0);
+
+        let (status, second) = page(1).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(second["results"][0]["chunk_id"], "b.rs#1-2");
+        let score = second["results"][0]["score"].as_f64().unwrap();
+        assert!(score > 0.0 && score < 1.0, "second page scored {}", score);
+    }
+
+    #[tokio::test]
+    async fn test_optimize_merges_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+
+        // Each write commits a segment; the first also holds a chunk deleted afterwards
+        let chunks = json!({ "chunks": [
+            { "chunk_id": "old.rs#1-2", "text": "segment" },
+            { "chunk_id": "f0.rs#1-2", "text": "segment" },
+        ]});
+        post_json(app.clone(), "/index/batch", chunks).await;
+        for i in 1..3 {
+            let chunk = json!({ "chunk_id": format!("f{}.rs#1-2", i), "text": "segment" });
+            post_json(app.clone(), "/index", chunk).await;
+        }
+        let request = Request::delete("/index/old.rs%231-2").body(Body::empty()).unwrap();
+        app.clone().oneshot(request).await.unwrap();
+
+        let (status, body) = post_json(app.clone(), "/index/optimize", json!({})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["segments_before"], 3);
+        assert_eq!(body["segments_after"], 1);
+        assert_eq!(body["deleted_docs_before"], 1);
+        assert_eq!(body["deleted_docs_after"], 0);
+
+        let (_, body) = post_json(app, "/search", json!({ "query": "segment" })).await;
+        assert_eq!(body["total_hits"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_write_limit_queues_then_rejects_writes_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = TantivyIndex::new(temp_dir.path().to_str().unwrap()).unwrap();
+        let state = Arc::new(AppState {
+            index,
+            commit_policy: CommitPolicy {
+                interval: Duration::from_secs(60),
+                max_pending: 0,
+            },
+            api_key: None,
+            data_dir: temp_dir.path().to_path_buf(),
+            write_limit: Some(WriteLimit::new(1, 1)),
+            max_body_bytes: DEFAULT_MAX_BODY_MB * 1024 * 1024,
+        });
+        let app = build_router(state.clone());
+        let limit = state.write_limit.as_ref().unwrap();
+        let chunk = |id: &str| json!({ "chunk_id": id, "text": "queued write" });
+
+        // Occupy the only slot, so the next write has to wait
+        let held = limit.permits.try_acquire().unwrap();
+        let waiting = tokio::spawn(post_json(app.clone(), "/index", chunk("a.rs#1-2")));
+        while limit.queued.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // The queue is full: writes are turned away, searches are not limited
+        let (status, _) = post_json(app.clone(), "/index", chunk("b.rs#1-2")).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        let (status, _) = post_json(app.clone(), "/search", json!({ "query": "queued" })).await;
+        assert_eq!(status, StatusCode::OK);
+
+        drop(held);
+        let (status, _) = waiting.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(limit.queued.load(Ordering::SeqCst), 0);
+        assert_eq!(state.index.doc_count(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_search_proceeds_during_batch_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+        post_json(app.clone(), "/index", json!({ "chunk_id": "a.rs#1-2", "text": "needle" })).await;
+
+        let chunks: Vec<_> = (0..20_000)
+            .map(|i| json!({ "chunk_id": format!("bulk{}.rs#1-2", i), "text": "haystack" }))
+            .collect();
+        let batch = tokio::spawn(post_json(app.clone(), "/index/batch", json!({ "chunks": chunks })));
+
+        let search = post_json(app.clone(), "/search", json!({ "query": "needle" }));
+        let (status, body) = tokio::time::timeout(Duration::from_secs(2), search)
+            .await
+            .expect("search blocked behind the batch index");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_hits"], 1);
+
+        let (status, _) = batch.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_stream_index_commits_in_batches() {
+        let temp_dir = TempDir::new().unwrap();
+        let state = test_state(&temp_dir);
+        let app = build_router(state.clone());
+
+        let mut body = String::new();
+        for i in 0..5 {
+            let text = if i == 4 { "" } else { "streamed" };
+            body.push_str(&json!({ "chunk_id": format!("s{}.rs#1-2", i), "text": text })
+                .to_string());
+            body.push('\n');
+            if i == 2 {
+                body.push_str("{ not json\n\n");
+                body.push_str(&json!({ "chunk_id": " ", "text": "no id" }).to_string());
+                body.push('\n');
+            }
+        }
+        let request = Request::post("/index/batch/stream?commit_every=2")
+            .header("content-type", "application/x-ndjson")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let events: Vec<Value> = String::from_utf8(bytes.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let statuses: Vec<_> = events.iter().map(|e| e["status"].as_str().unwrap()).collect();
+        assert_eq!(
+            statuses,
+            vec!["progress", "error", "error", "progress", "warning", "success"]
+        );
+        assert_eq!(events[1]["line"], 4);
+        assert_eq!(events[2]["line"], 6);
+        assert_eq!(events[2]["error"], "chunk_id must not be empty");
+        assert_eq!(events[3]["indexed"], 4);
+        assert_eq!(events[4]["line"], 8);
+        let summary = &events[5];
+        assert_eq!(summary["indexed"], 5);
+        let error_lines: Vec<_> = summary["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["line"].clone())
+            .collect();
+        assert_eq!(error_lines, vec![json!(4), json!(6)]);
+        assert_eq!(state.index.doc_count(), 5);
+    }
+
+    /// A streaming request body fed by the returned sender
+    fn channel_body() -> (mpsc::Sender<String>, Body) {
+        let (sender, receiver) = mpsc::channel::<String>(4);
+        let chunks = stream::unfold(receiver, |mut receiver| async move {
+            let chunk = receiver.recv().await?;
+            Some((Ok::<_, std::convert::Infallible>(chunk), receiver))
+        });
+        (sender, Body::from_stream(chunks))
+    }
+
+    #[tokio::test]
+    async fn test_stream_holds_write_slot_until_done() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut state = Arc::into_inner(test_state(&temp_dir)).unwrap();
+        state.write_limit = Some(WriteLimit::new(1, 0));
+        let app = build_router(Arc::new(state));
+        let chunk = |id: &str| json!({ "chunk_id": id, "text": "streamed" }).to_string() + "\n";
+
+        let (sender, body) = channel_body();
+        sender.send(chunk("a.rs#1-2")).await.unwrap();
+        let request = Request::post("/index/batch/stream").body(body).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The handler has returned, but the upload is still being indexed
+        let single = json!({ "chunk_id": "b.rs#1-2", "text": "single" });
+        let (status, _) = post_json(app.clone(), "/index", single.clone()).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+        drop(sender);
+        axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let (status, _) = post_json(app, "/index", single).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_stream_skips_lines_over_the_body_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut state = Arc::into_inner(test_state(&temp_dir)).unwrap();
+        state.max_body_bytes = 1024;
+        let state = Arc::new(state);
+        let app = build_router(state.clone());
+        let chunk = |id: &str, text: &str| json!({ "chunk_id": id, "text": text }).to_string();
+
+        // Line 2 arrives across three reads and is dropped as it comes in;
+        // line 4 arrives whole
+        let long = chunk("long.rs#1-2", &"x".repeat(3000));
+        let (sender, body) = channel_body();
+        sender.send(format!("{}\n{}", chunk("a.rs#1-2", "kept"), &long[..1500])).await.unwrap();
+        sender.send(long[1500..2500].to_string()).await.unwrap();
+        let tail = format!("{}\n{}\n{}\n", &long[2500..], chunk("b.rs#1-2", "kept"), long);
+        sender.send(tail).await.unwrap();
+        drop(sender);
+
+        let request = Request::post("/index/batch/stream").body(body).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let events: Vec<Value> = String::from_utf8(bytes.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let summary = events.last().unwrap();
+        assert_eq!(summary["status"], "success");
+        assert_eq!(summary["indexed"], 2);
+        let error_lines: Vec<_> =
+            summary["errors"].as_array().unwrap().iter().map(|e| e["line"].clone()).collect();
+        assert_eq!(error_lines, vec![json!(2), json!(4)]);
+        assert!(summary["errors"][0]["error"].as_str().unwrap().contains("1024 byte limit"));
+        assert_eq!(state.index.doc_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_delete_requires_ids_or_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+
+        let (status, _) = post_json(app.clone(), "/index/delete/batch", json!({})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = post_json(app, "/index/delete/batch", json!({ "prefix": "" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_index_rejects_empty_chunk_ids_and_flags_empty_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+
+        let blank_id = json!({ "chunk_id": " ", "text": "tokio" });
+        let (status, _) = post_json(app.clone(), "/index", blank_id.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // One bad chunk rejects the whole batch
+        let chunks = json!({ "chunks": [{ "chunk_id": "a.rs#1-2", "text": "tokio" }, blank_id] });
+        let (status, _) = post_json(app.clone(), "/index/batch", chunks).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (_, body) = post_json(app.clone(), "/search", json!({ "query": "tokio" })).await;
+        assert_eq!(body["total_hits"], 0);
+
+        let (status, body) =
+            post_json(app.clone(), "/index", json!({ "chunk_id": "a.rs#1-2", "text": "tokio" }))
+                .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.get("empty_text").is_none());
+
+        let chunks = json!({ "chunks": [
+            { "chunk_id": "b.rs#1-2", "text": "  " },
+            { "chunk_id": "c.rs#1-2", "text": "runtime" },
+        ]});
+        let (status, body) = post_json(app, "/index/batch", chunks).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["indexed"], 2);
+        assert_eq!(body["empty_text"], json!(["b.rs#1-2"]));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_gets_json_413() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut state = Arc::into_inner(test_state(&temp_dir)).unwrap();
+        state.max_body_bytes = 1024 * 1024;
+        let app = build_router(Arc::new(state));
+
+        let big = "x".repeat(2 * 1024 * 1024);
+        let chunks = json!({ "chunks": [{ "chunk_id": "big.rs#1-1", "text": big }] });
+        let (status, body) = post_json(app.clone(), "/index/batch", chunks).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["limit_bytes"], 1024 * 1024);
+        assert!(body["error"].as_str().unwrap().contains("/index/batch/stream"));
+
+        let small = json!({ "chunks": [{ "chunk_id": "a.rs#1-1", "text": "fn small() {}" }] });
+        let (status, _) = post_json(app.clone(), "/index/batch", small).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Searches have their own, smaller limit
+        let query = json!({ "query": "q".repeat(SEARCH_MAX_BODY_BYTES) });
+        let (status, _) = post_json(app, "/search", query).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_required_except_for_health() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state_with_key(&temp_dir, Some("secret")));
+
+        let search = |auth: Option<&str>| {
+            let mut request = Request::post("/search").header("content-type", "application/json");
+            if let Some(auth) = auth {
+                request = request.header("authorization", auth);
+            }
+            request.body(Body::from(json!({ "query": "tokio" }).to_string())).unwrap()
+        };
+
+        let response = app.clone().oneshot(search(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.clone().oneshot(search(Some("Bearer wrong"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.clone().oneshot(search(Some("Bearer secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let health = Request::get("/health").body(Body::empty()).unwrap();
+        let response = app.oneshot(health).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_document_by_chunk_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let app = build_router(test_state(&temp_dir));
+
+        let chunk = json!({ "chunk_id": "src/a.rs#1-2", "text": "fn a() {}", "path": "src/a.rs" });
+        post_json(app.clone(), "/index", chunk).await;
+
+        // Chunk ids contain slashes and '#', so clients percent-encode them
+        let request = Request::get("/index/src%2Fa.rs%231-2/doc").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["text"], "fn a() {}");
+        assert_eq!(body["path"], "src/a.rs");
+
+        let request = Request::get("/index/missing/doc").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
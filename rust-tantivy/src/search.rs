@@ -3,51 +3,413 @@
 //! Additional search functionality beyond basic BM25.
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::index::SearchHit;
+
+/// Tantivy's built-in BM25 term-frequency saturation
+pub const DEFAULT_BM25_K1: f32 = 1.2;
+
+/// Tantivy's built-in BM25 length normalization
+pub const DEFAULT_BM25_B: f32 = 0.75;
+
+/// How bare multi-term queries combine their terms
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Conjunction {
+    /// `async runtime` matches docs containing either term (default)
+    #[default]
+    Or,
+    /// `async runtime` only matches docs containing both terms, like grep
+    And,
+}
+
+/// How query terms are matched against indexed terms
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Full query syntax via the query parser (default)
+    #[default]
+    Standard,
+    /// Each term matches indexed terms starting with it, so `tant` finds `tantivy`
+    Prefix,
+    /// Each term matches indexed terms within edit distance 1
+    Fuzzy,
+}
+
+/// How many of a query's optional terms a document must contain
+///
+/// Given as a count (`3`) or a percentage of the terms (`"75%"`, rounded
+/// down but never below one term).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "MinMatchValue", into = "MinMatchValue")]
+pub enum MinMatch {
+    Count(usize),
+    Percent(u8),
+}
+
+/// JSON form of [`MinMatch`]: a number, or a string such as `"3"` or `"75%"`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum MinMatchValue {
+    Count(usize),
+    Text(String),
+}
+
+impl MinMatch {
+    /// Parse `3` or `75%`; counts start at 1 and percentages run from 1 to 100
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if let Some(percent) = value.strip_suffix('%') {
+            return match percent.trim().parse::<u8>() {
+                Ok(percent @ 1..=100) => Ok(MinMatch::Percent(percent)),
+                _ => Err(format!("min_match percentage must be 1% to 100%, got {:?}", value)),
+            };
+        }
+        match value.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(MinMatch::Count(count)),
+            _ => Err(format!(
+                "min_match must be a count of at least 1 or a percentage, got {:?}",
+                value
+            )),
+        }
+    }
+    
+    /// Number of `terms` optional terms that must match
+    pub fn required(&self, terms: usize) -> Result<usize, String> {
+        match *self {
+            MinMatch::Count(count) if count > terms => Err(format!(
+                "min_match {} exceeds the {} optional terms in the query",
+                count, terms
+            )),
+            MinMatch::Count(count) => Ok(count),
+            // At least one term, unless there are none to require
+            MinMatch::Percent(percent) => Ok((terms * percent as usize / 100).max(1).min(terms)),
+        }
+    }
+}
+
+impl TryFrom<MinMatchValue> for MinMatch {
+    type Error = String;
+    
+    fn try_from(value: MinMatchValue) -> Result<Self, String> {
+        match value {
+            MinMatchValue::Count(count) => MinMatch::parse(&count.to_string()),
+            MinMatchValue::Text(text) => MinMatch::parse(&text),
+        }
+    }
+}
+
+impl From<MinMatch> for MinMatchValue {
+    fn from(min_match: MinMatch) -> Self {
+        match min_match {
+            MinMatch::Count(count) => MinMatchValue::Count(count),
+            MinMatch::Percent(percent) => MinMatchValue::Text(format!("{}%", percent)),
+        }
+    }
+}
+
+/// Default over-fetch multiplier for `dedup_by_path`
+pub const DEFAULT_OVERFETCH: usize = 2;
 
 /// Search configuration options
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct SearchConfig {
     /// Maximum number of results to return
     pub limit: usize,
     
-    /// Minimum score threshold (0.0 - 1.0)
+    /// Number of top-ranked results to skip (for pagination)
+    pub offset: usize,
+    
+    /// Minimum score threshold (0.0 - 1.0 when `normalize_scores` is set,
+    /// otherwise compared against raw BM25 scores)
     pub min_score: Option<f32>,
     
-    /// Whether to highlight matches
+    /// Divide scores by the query's top score so they fall in 0.0 - 1.0 and
+    /// stay comparable across pages
+    pub normalize_scores: bool,
+    
+    /// Report the line of each hit's first matched term as `match_line`
     pub highlight: bool,
+    
+    /// Operator between unqualified query terms; `or` keeps the historical behavior
+    pub default_conjunction: Conjunction,
+    
+    /// Only return chunks from this store; searches all stores when unset
+    pub store: Option<String>,
+    
+    /// Only return chunks from any of these stores, ranked together; adds to
+    /// `store` when both are given
+    pub stores: Vec<String>,
+    
+    /// Only return chunks in any of these languages; searches all when empty
+    pub languages: Vec<String>,
+    
+    /// Keep only the best-scoring chunk of each file; a page may then hold
+    /// fewer than `limit` results even when more files match
+    pub dedup_by_path: bool,
+    
+    /// With `dedup_by_path`, fetch this many times `offset + limit` hits
+    /// before collapsing. Higher values trade latency for full pages when
+    /// files have many matching chunks; other filters are part of the query
+    /// and never need it.
+    pub overfetch: usize,
+    
+    /// Term matching strategy; `prefix` and `fuzzy` ignore query syntax
+    pub match_mode: MatchMode,
+    
+    /// For a query that is a single quoted phrase, how many positions its
+    /// terms may be moved to still match; 0 is an exact phrase. Only applies
+    /// in `standard` mode.
+    pub phrase_slop: Option<u32>,
+    
+    /// With the `or` conjunction, how many of the query's terms a document
+    /// must contain: a count or a percentage such as `"75%"`. Sharpens long
+    /// queries without requiring every term.
+    pub min_match: Option<MinMatch>,
+    
+    /// Match terms with their case, so `User` no longer finds `user`. Terms
+    /// are compared as whole tokens, which implies `whole_word`.
+    pub case_sensitive: bool,
+    
+    /// Match whole tokens only: `user` finds `User` but not `getUserName`,
+    /// and no stemming applies. Like `case_sensitive`, it matches single
+    /// words, so phrases are rejected.
+    pub whole_word: bool,
+    
+    /// BM25 term-frequency saturation; higher values reward repeated terms more.
+    /// Custom `bm25_k1` or `bm25_b` need `standard` match mode.
+    pub bm25_k1: f32,
+    
+    /// BM25 length normalization, from 0.0 (none) to 1.0 (full)
+    pub bm25_b: f32,
 }
 
 impl Default for SearchConfig {
     fn default() -> Self {
         Self {
             limit: 10,
+            offset: 0,
             min_score: None,
+            normalize_scores: false,
             highlight: false,
+            default_conjunction: Conjunction::Or,
+            store: None,
+            stores: Vec::new(),
+            languages: Vec::new(),
+            dedup_by_path: false,
+            overfetch: DEFAULT_OVERFETCH,
+            match_mode: MatchMode::Standard,
+            phrase_slop: None,
+            min_match: None,
+            case_sensitive: false,
+            whole_word: false,
+            bm25_k1: DEFAULT_BM25_K1,
+            bm25_b: DEFAULT_BM25_B,
         }
     }
 }
 
+impl SearchConfig {
+    /// Reject parameter values that would produce meaningless scores or pages
+    pub fn validate(&self) -> Result<(), String> {
+        if self.limit == 0 {
+            return Err("limit must be at least 1".to_string());
+        }
+        if self.offset.checked_add(self.limit).is_none() {
+            return Err(format!("offset {} is too large", self.offset));
+        }
+        if self.overfetch == 0 {
+            return Err("overfetch must be at least 1".to_string());
+        }
+        if !self.bm25_k1.is_finite() || self.bm25_k1 < 0.0 {
+            return Err(format!("bm25_k1 must be >= 0, got {}", self.bm25_k1));
+        }
+        if !(0.0..=1.0).contains(&self.bm25_b) {
+            return Err(format!("bm25_b must be between 0 and 1, got {}", self.bm25_b));
+        }
+        if self.min_match.is_some() && self.default_conjunction == Conjunction::And {
+            return Err("min_match only applies with default_conjunction or".to_string());
+        }
+        // Prefix and fuzzy matching widen terms, which is what these options rule out
+        if (self.case_sensitive || self.whole_word) && self.match_mode != MatchMode::Standard {
+            return Err(
+                "case_sensitive and whole_word need match_mode standard, not prefix or fuzzy"
+                    .to_string(),
+            );
+        }
+        // Their terms are only expanded per segment, past where BM25 is rescored
+        if self.has_custom_bm25() && self.match_mode != MatchMode::Standard {
+            return Err(
+                "bm25_k1 and bm25_b need match_mode standard, not prefix or fuzzy".to_string(),
+            );
+        }
+        if (self.case_sensitive || self.whole_word) && self.phrase_slop.is_some() {
+            return Err(
+                "case_sensitive and whole_word can't be combined with phrase_slop".to_string(),
+            );
+        }
+        Ok(())
+    }
+    
+    /// Whether scoring needs BM25 parameters other than Tantivy's built-in ones
+    pub fn has_custom_bm25(&self) -> bool {
+        self.bm25_k1 != DEFAULT_BM25_K1 || self.bm25_b != DEFAULT_BM25_B
+    }
+}
+
+/// Scale scores relative to `top_score`, the best score of the whole query,
+/// so only the overall top result scores 1.0 and later pages stay below it
+pub fn normalize_scores(mut results: Vec<SearchHit>, top_score: Option<f32>) -> Vec<SearchHit> {
+    let max_score = top_score.unwrap_or(0.0);
+    if max_score > 0.0 {
+        for hit in &mut results {
+            hit.score /= max_score;
+        }
+    }
+    results
+}
+
+/// Keep the first (highest-ranked) hit for each file, preserving order
+///
+/// Hits without a stored path fall back to the `path#range` chunk id prefix.
+pub fn dedup_by_path(results: Vec<SearchHit>) -> Vec<SearchHit> {
+    let mut seen = HashSet::new();
+    results
+        .into_iter()
+        .filter(|hit| {
+            let path = if hit.metadata.path.is_empty() {
+                hit.chunk_id.split('#').next().unwrap_or_default()
+            } else {
+                hit.metadata.path.as_str()
+            };
+            seen.insert(path.to_string())
+        })
+        .collect()
+}
+
 /// Filter results by minimum score
-pub fn filter_by_score(results: Vec<(String, f32)>, min_score: f32) -> Vec<(String, f32)> {
+pub fn filter_by_score(results: Vec<SearchHit>, min_score: f32) -> Vec<SearchHit> {
     results
         .into_iter()
-        .filter(|(_, score)| *score >= min_score)
+        .filter(|hit| hit.score >= min_score)
         .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::ChunkMetadata;
+    
+    fn hit(chunk_id: &str, score: f32) -> SearchHit {
+        SearchHit {
+            chunk_id: chunk_id.to_string(),
+            score,
+            metadata: ChunkMetadata::default(),
+            match_line: None,
+        }
+    }
     
     #[test]
     fn test_filter_by_score() {
-        let results = vec![
-            ("a".to_string(), 0.9),
-            ("b".to_string(), 0.5),
-            ("c".to_string(), 0.3),
-        ];
+        let results = vec![hit("a", 0.9), hit("b", 0.5), hit("c", 0.3)];
         
         let filtered = filter_by_score(results, 0.4);
         assert_eq!(filtered.len(), 2);
     }
+    
+    #[test]
+    fn test_dedup_by_path() {
+        let results = vec![
+            hit("src/a.rs#1-10", 9.0),
+            hit("src/b.rs#1-10", 7.0),
+            hit("src/a.rs#11-20", 5.0),
+            hit("src/a.rs#21-30", 3.0),
+            hit("src/c.rs#1-10", 1.0),
+        ];
+        
+        let ids: Vec<_> = dedup_by_path(results).into_iter().map(|h| h.chunk_id).collect();
+        assert_eq!(ids, vec!["src/a.rs#1-10", "src/b.rs#1-10", "src/c.rs#1-10"]);
+    }
+    
+    #[test]
+    fn test_normalize_scores() {
+        let results = vec![hit("a", 8.0), hit("b", 4.0), hit("c", 2.0)];
+        
+        let normalized = normalize_scores(results, Some(8.0));
+        assert_eq!(normalized[0].score, 1.0);
+        assert_eq!(normalized[1].score, 0.5);
+        assert_eq!(normalized[2].score, 0.25);
+        
+        // min_score now keeps its 0.0 - 1.0 meaning
+        assert_eq!(filter_by_score(normalized, 0.5).len(), 2);
+        
+        assert!(normalize_scores(Vec::new(), None).is_empty());
+        
+        // A later page is scaled by the query's best score, not its own
+        let page = normalize_scores(vec![hit("d", 4.0), hit("e", 2.0)], Some(8.0));
+        assert_eq!(page[0].score, 0.5);
+        assert_eq!(page[1].score, 0.25);
+    }
+    
+    #[test]
+    fn test_min_match_parsing() {
+        assert_eq!(MinMatch::parse("3"), Ok(MinMatch::Count(3)));
+        assert_eq!(MinMatch::parse("75%"), Ok(MinMatch::Percent(75)));
+        for invalid in ["0", "0%", "101%", "-1", "half"] {
+            assert!(MinMatch::parse(invalid).is_err(), "{}", invalid);
+        }
+        
+        let config: SearchConfig = serde_json::from_str(r#"{"min_match": 2}"#).unwrap();
+        assert_eq!(config.min_match, Some(MinMatch::Count(2)));
+        let config: SearchConfig = serde_json::from_str(r#"{"min_match": "50%"}"#).unwrap();
+        assert_eq!(config.min_match, Some(MinMatch::Percent(50)));
+        assert!(serde_json::from_str::<SearchConfig>(r#"{"min_match": 0}"#).is_err());
+        
+        assert_eq!(MinMatch::Count(3).required(4), Ok(3));
+        assert!(MinMatch::Count(5).required(4).is_err());
+        assert_eq!(MinMatch::Percent(75).required(4), Ok(3));
+        assert_eq!(MinMatch::Percent(10).required(4), Ok(1));
+    }
+    
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: SearchConfig = serde_json::from_str(r#"{"limit": 5, "offset": 20}"#).unwrap();
+        assert_eq!(config.limit, 5);
+        assert_eq!(config.offset, 20);
+        assert!(config.min_score.is_none());
+        assert!(!config.normalize_scores);
+        assert!(!config.highlight);
+        assert_eq!(config.default_conjunction, Conjunction::Or);
+        assert!(!config.has_custom_bm25());
+    }
+    
+    #[test]
+    fn test_validate_bm25_params() {
+        let config = |k1: f32, b: f32| SearchConfig {
+            bm25_k1: k1,
+            bm25_b: b,
+            ..Default::default()
+        };
+        
+        assert!(SearchConfig::default().validate().is_ok());
+        assert!(config(2.0, 0.0).validate().is_ok());
+        assert!(config(-1.0, 0.5).validate().is_err());
+        assert!(config(1.2, 1.5).validate().is_err());
+        assert!(config(f32::NAN, 0.5).validate().is_err());
+        
+        let fuzzy = SearchConfig { match_mode: MatchMode::Fuzzy, ..config(2.0, 0.5) };
+        assert!(fuzzy.validate().is_err());
+        let prefix = SearchConfig { match_mode: MatchMode::Prefix, ..Default::default() };
+        assert!(prefix.validate().is_ok());
+        
+        let no_fetch = SearchConfig { overfetch: 0, ..Default::default() };
+        assert!(no_fetch.validate().is_err());
+        
+        let empty_page = SearchConfig { limit: 0, ..Default::default() };
+        assert!(empty_page.validate().is_err());
+        let past_the_end = SearchConfig { offset: usize::MAX, ..Default::default() };
+        assert!(past_the_end.validate().is_err());
+    }
 }
@@ -0,0 +1,256 @@
+//! Text Tokenizers
+//!
+//! Custom analyzers for the `text` field. The `code` analyzer additionally
+//! indexes the CamelCase parts of identifiers, so `getUserName` is found by
+//! `user` or `name`; snake_case is already split on `_`. `code_stem` also
+//! reduces English words to their stem so `running` matches `run`.
+//!
+//! Whatever the `text` analyzer, every chunk is also indexed as whole words:
+//! once lowercased (Tantivy's `default`) and once with case kept (`cased`).
+//! These back the `whole_word` and `case_sensitive` search options. They are
+//! indexed without positions, so they only serve single-word matches.
+//!
+//! Tantivy does not persist custom analyzers, so they are registered every
+//! time an index is opened. The analyzer name is stored in the schema, which
+//! keeps indexing and query parsing on the same analyzer.
+
+use tantivy::tokenizer::{
+    Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, TextAnalyzer, Token,
+    TokenFilter, TokenStream, Tokenizer, TokenizerManager,
+};
+
+use crate::index::IndexError;
+
+/// Analyzer applied to the `text` field of newly created indexes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextTokenizer {
+    /// Tantivy's built-in analyzer: alphanumeric runs, lowercased
+    Default,
+    /// `Default` plus CamelCase subtokens
+    #[default]
+    Code,
+    /// `Code` plus English stemming
+    CodeStem,
+}
+
+impl TextTokenizer {
+    /// Name the analyzer is registered (and stored in the schema) under
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextTokenizer::Default => "default",
+            TextTokenizer::Code => "code",
+            TextTokenizer::CodeStem => "code_stem",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "default" => Some(TextTokenizer::Default),
+            "code" => Some(TextTokenizer::Code),
+            "code_stem" => Some(TextTokenizer::CodeStem),
+            _ => None,
+        }
+    }
+
+    /// Read `TANTIVY_TOKENIZER`, falling back to `code`
+    pub fn from_env() -> Result<Self, IndexError> {
+        match std::env::var("TANTIVY_TOKENIZER") {
+            Ok(value) => Self::parse(&value).ok_or_else(|| {
+                IndexError::Config(format!(
+                    "TANTIVY_TOKENIZER must be default, code or code_stem, got {:?}",
+                    value
+                ))
+            }),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+/// Whole tokens with their case kept; used by the `text_cased` field
+pub const CASED: &str = "cased";
+
+/// Register the custom analyzers with an index's tokenizer manager
+pub fn register(manager: &TokenizerManager) {
+    manager.register(
+        TextTokenizer::Code.name(),
+        TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(RemoveLongFilter::limit(40))
+            .filter(CamelCaseSplitter)
+            .filter(LowerCaser)
+            .build(),
+    );
+    manager.register(
+        TextTokenizer::CodeStem.name(),
+        TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(RemoveLongFilter::limit(40))
+            .filter(CamelCaseSplitter)
+            .filter(LowerCaser)
+            .filter(Stemmer::new(Language::English))
+            .build(),
+    );
+    manager.register(
+        CASED,
+        TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(RemoveLongFilter::limit(40))
+            .build(),
+    );
+}
+
+/// Byte offsets where a new CamelCase word starts (`getUser` -> 3, `HTTPServer` -> 4)
+fn camel_case_cuts(text: &str) -> Vec<usize> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut cuts = Vec::new();
+    for i in 1..chars.len() {
+        let (offset, c) = chars[i];
+        if !c.is_uppercase() {
+            continue;
+        }
+        let prev = chars[i - 1].1;
+        let next = chars.get(i + 1).map(|&(_, next)| next);
+        let after_lower = prev.is_lowercase() || prev.is_numeric();
+        let ends_acronym = prev.is_uppercase() && next.is_some_and(char::is_lowercase);
+        if after_lower || ends_acronym {
+            cuts.push(offset);
+        }
+    }
+    cuts
+}
+
+/// Emits each token followed by its CamelCase parts, all at the token's position
+#[derive(Clone)]
+pub struct CamelCaseSplitter;
+
+impl TokenFilter for CamelCaseSplitter {
+    type Tokenizer<T: Tokenizer> = CamelCaseSplitterFilter<T>;
+
+    fn transform<T: Tokenizer>(self, tokenizer: T) -> CamelCaseSplitterFilter<T> {
+        CamelCaseSplitterFilter {
+            inner: tokenizer,
+            parts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CamelCaseSplitterFilter<T> {
+    inner: T,
+    parts: Vec<Token>,
+}
+
+impl<T: Tokenizer> Tokenizer for CamelCaseSplitterFilter<T> {
+    type TokenStream<'a> = CamelCaseSplitterTokenStream<'a, T::TokenStream<'a>>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        self.parts.clear();
+        CamelCaseSplitterTokenStream {
+            tail: self.inner.token_stream(text),
+            parts: &mut self.parts,
+        }
+    }
+}
+
+pub struct CamelCaseSplitterTokenStream<'a, T> {
+    tail: T,
+    /// Pending tokens in reverse order; the last one is current
+    parts: &'a mut Vec<Token>,
+}
+
+impl<T: TokenStream> CamelCaseSplitterTokenStream<'_, T> {
+    fn split(&mut self) {
+        let token = self.tail.token();
+        let cuts = camel_case_cuts(&token.text);
+        if cuts.is_empty() {
+            return;
+        }
+
+        let mut end = token.text.len();
+        for &start in cuts.iter().rev().chain(std::iter::once(&0)) {
+            self.parts.push(Token {
+                offset_from: token.offset_from + start,
+                offset_to: token.offset_from + end,
+                text: token.text[start..end].to_string(),
+                ..*token
+            });
+            end = start;
+        }
+        // The whole identifier comes first so exact symbol queries still match
+        self.parts.push(token.clone());
+    }
+}
+
+impl<T: TokenStream> TokenStream for CamelCaseSplitterTokenStream<'_, T> {
+    fn advance(&mut self) -> bool {
+        self.parts.pop();
+        if !self.parts.is_empty() {
+            return true;
+        }
+        if !self.tail.advance() {
+            return false;
+        }
+        self.split();
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.parts.last().unwrap_or_else(|| self.tail.token())
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.parts.last_mut().unwrap_or_else(|| self.tail.token_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(tokenizer: TextTokenizer, text: &str) -> Vec<String> {
+        let manager = TokenizerManager::default();
+        register(&manager);
+        let mut analyzer = manager.get(tokenizer.name()).unwrap();
+        let mut stream = analyzer.token_stream(text);
+        let mut tokens = Vec::new();
+        while let Some(token) = stream.next() {
+            tokens.push(token.text.clone());
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_code_splits_identifiers() {
+        assert_eq!(
+            tokens(TextTokenizer::Code, "getUserName(user_id)"),
+            vec!["getusername", "get", "user", "name", "user", "id"]
+        );
+        assert_eq!(
+            tokens(TextTokenizer::Code, "HTTPServer utf8Decode plain"),
+            vec!["httpserver", "http", "server", "utf8decode", "utf8", "decode", "plain"]
+        );
+    }
+
+    #[test]
+    fn test_code_stem_reduces_words() {
+        assert_eq!(tokens(TextTokenizer::CodeStem, "running parsers"), vec!["run", "parser"]);
+    }
+
+    #[test]
+    fn test_cased_keeps_whole_tokens() {
+        let manager = TokenizerManager::default();
+        register(&manager);
+        let mut analyzer = manager.get(CASED).unwrap();
+        let mut stream = analyzer.token_stream("getUser User");
+        let mut tokens = Vec::new();
+        while let Some(token) = stream.next() {
+            tokens.push(token.text.clone());
+        }
+        assert_eq!(tokens, vec!["getUser", "User"]);
+    }
+
+    #[test]
+    fn test_parse_names() {
+        for tokenizer in [TextTokenizer::Default, TextTokenizer::Code, TextTokenizer::CodeStem] {
+            assert_eq!(TextTokenizer::parse(tokenizer.name()), Some(tokenizer));
+        }
+        assert_eq!(TextTokenizer::parse("english"), None);
+    }
+}